@@ -0,0 +1,18 @@
+#[allow(dead_code)]
+mod item {
+    #[derive(serde::Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+    pub enum ItemKind { Waste, Crop, Fuel, Tool, Water }
+}
+#[allow(dead_code)]
+mod ship {
+    include!("/root/crate/src/ship.rs");
+    pub fn check(b: &[u8]) -> Result<usize, ShipLayoutError> {
+        let l: ShipLayout = ron::de::from_bytes(b)?;
+        l.validate()?;
+        Ok(l.tiles.iter().map(|t| t.positions().count()).sum())
+    }
+}
+fn main() {
+    let path = std::env::args().nth(1).unwrap();
+    println!("{:?}", ship::check(&std::fs::read(path).unwrap()).map_err(|e| e.to_string()));
+}
@@ -0,0 +1,3 @@
+#[allow(dead_code)]
+mod loading { pub const TILE_SIZE: f32 = 18.; pub struct ImageAssets; pub struct LevelAssets; }
+fn main() {}
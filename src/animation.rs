@@ -1,10 +1,11 @@
+use crate::aseprite::Aseprite;
 use bevy::prelude::*;
 
 pub struct SpriteAnimationPlugin;
 
 impl Plugin for SpriteAnimationPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Update, animate_sprite);
+        app.add_systems(Update, (animate_sprite, apply_aseprite_animations));
     }
 }
 
@@ -17,6 +18,14 @@ pub struct AnimationIndices {
 #[derive(Component, Deref, DerefMut)]
 pub struct AnimationTimer(pub Timer);
 
+/// Tag of an Aseprite file to take the frames of the sprite animation from;
+/// applied once the file is loaded and again whenever it changes
+#[derive(Component)]
+pub struct AsepriteAnimation {
+    pub aseprite: Handle<Aseprite>,
+    pub tag: String,
+}
+
 fn animate_sprite(
     time: Res<Time>,
     mut to_animate: Query<(&AnimationIndices, &mut AnimationTimer, &mut TextureAtlas)>,
@@ -32,3 +41,48 @@ fn animate_sprite(
         }
     }
 }
+
+#[allow(clippy::type_complexity)]
+fn apply_aseprite_animations(
+    mut asset_events: EventReader<AssetEvent<Aseprite>>,
+    aseprites: Res<Assets<Aseprite>>,
+    mut animated: Query<(
+        Ref<AsepriteAnimation>,
+        &mut Handle<Image>,
+        &mut TextureAtlas,
+        &mut AnimationIndices,
+        &mut AnimationTimer,
+    )>,
+) {
+    let changed: Vec<AssetId<Aseprite>> = asset_events
+        .read()
+        .filter_map(|event| match *event {
+            AssetEvent::LoadedWithDependencies { id } | AssetEvent::Modified { id } => Some(id),
+            _ => None,
+        })
+        .collect();
+    for (animation, mut texture, mut atlas, mut indices, mut timer) in &mut animated {
+        if !animation.is_added() && !changed.contains(&animation.aseprite.id()) {
+            continue;
+        }
+        let Some(aseprite) = aseprites.get(&animation.aseprite) else {
+            continue;
+        };
+        let Some((new_indices, duration)) = aseprite.animation(&animation.tag) else {
+            let path = animation.aseprite.path().map(ToString::to_string);
+            error!(
+                "{} has no animation tag '{}', keeping the current animation",
+                path.as_deref().unwrap_or("Aseprite file"),
+                animation.tag
+            );
+            continue;
+        };
+        *texture = aseprite.atlas.clone();
+        atlas.layout = aseprite.layout.clone();
+        *indices = new_indices;
+        timer.set_duration(duration);
+        if !(indices.first..=indices.last).contains(&atlas.index) {
+            atlas.index = indices.first;
+        }
+    }
+}
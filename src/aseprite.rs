@@ -0,0 +1,751 @@
+use crate::animation::AnimationIndices;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat};
+use bevy::utils::HashMap;
+use std::time::Duration;
+use thiserror::Error;
+
+const HEADER_MAGIC: u16 = 0xA5E0;
+const FRAME_MAGIC: u16 = 0xF1FA;
+const HEADER_SIZE: usize = 128;
+
+const OLD_PALETTE_CHUNK: u16 = 0x0004;
+const LAYER_CHUNK: u16 = 0x2004;
+const CEL_CHUNK: u16 = 0x2005;
+const TAGS_CHUNK: u16 = 0x2018;
+const PALETTE_CHUNK: u16 = 0x2019;
+
+const LAYER_VISIBLE: u16 = 1;
+const NORMAL_LAYER: u16 = 0;
+
+pub struct AsepritePlugin;
+
+impl Plugin for AsepritePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<Aseprite>()
+            .init_asset_loader::<AsepriteLoader>();
+    }
+}
+
+/// An Aseprite file (`.aseprite` or `.ase`)
+///
+/// Every visible layer of the first frame is available as an image, e.g. to generate the map from.
+/// All frames are combined into a texture atlas and tags are animation clips on that atlas.
+#[derive(Asset, TypePath, Debug)]
+pub struct Aseprite {
+    pub layers: Vec<AsepriteLayer>,
+    /// All frames next to each other, to be used with `layout`
+    pub atlas: Handle<Image>,
+    pub layout: Handle<TextureAtlasLayout>,
+    pub frame_durations: Vec<Duration>,
+    pub tags: Vec<AsepriteTag>,
+}
+
+#[derive(Debug)]
+pub struct AsepriteLayer {
+    pub name: String,
+    pub image: Image,
+}
+
+#[derive(Debug, Clone)]
+pub struct AsepriteTag {
+    pub name: String,
+    pub first: usize,
+    pub last: usize,
+}
+
+impl Aseprite {
+    /// Frame indices and frame duration of the animation tagged with `name`;
+    /// `None` if there is no such tag or its frames are out of range
+    pub fn animation(&self, name: &str) -> Option<(AnimationIndices, Duration)> {
+        let tag = self.tags.iter().find(|tag| tag.name == name)?;
+        if tag.first > tag.last || tag.last >= self.frame_durations.len() {
+            return None;
+        }
+        let duration = self.frame_durations[tag.first];
+
+        Some((
+            AnimationIndices {
+                first: tag.first,
+                last: tag.last,
+            },
+            duration,
+        ))
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AsepriteError {
+    #[error("Could not read Aseprite file: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Not an Aseprite file")]
+    InvalidMagic,
+    #[error("Aseprite file ended unexpectedly")]
+    UnexpectedEnd,
+    #[error("Unsupported color depth of {0} bits per pixel")]
+    UnsupportedColorDepth(u16),
+    #[error("Could not decompress cel: {0:?}")]
+    Decompress(miniz_oxide::inflate::TINFLStatus),
+    #[error("Cel in frame {frame} links to the missing frame {linked}")]
+    MissingLinkedCel { frame: usize, linked: usize },
+}
+
+#[derive(Default)]
+struct AsepriteLoader;
+
+impl AssetLoader for AsepriteLoader {
+    type Asset = Aseprite;
+    type Settings = ();
+    type Error = AsepriteError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let file = AsepriteFile::parse(&bytes)?;
+
+        let layers = file
+            .layers
+            .iter()
+            .enumerate()
+            .filter(|(_, layer)| layer.is_drawn())
+            .map(|(index, layer)| AsepriteLayer {
+                name: layer.name.clone(),
+                image: file.image(file.canvas(0, |layer| layer == index)),
+            })
+            .collect();
+
+        let frames = file.frame_durations.len();
+        let mut atlas = vec![0; file.width * frames * file.height * 4];
+        for frame in 0..frames {
+            let canvas = file.canvas(frame, |layer| file.layers[layer].is_drawn());
+            for (row, pixels) in canvas.chunks_exact(file.width * 4).enumerate() {
+                let start = (row * frames + frame) * file.width * 4;
+                atlas[start..start + pixels.len()].copy_from_slice(pixels);
+            }
+        }
+        let atlas = Image::new(
+            Extent3d {
+                width: (file.width * frames) as u32,
+                height: file.height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            atlas,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        );
+        let layout = TextureAtlasLayout::from_grid(
+            UVec2::new(file.width as u32, file.height as u32),
+            frames as u32,
+            1,
+            None,
+            None,
+        );
+
+        Ok(Aseprite {
+            layers,
+            atlas: load_context.add_labeled_asset("atlas".to_owned(), atlas),
+            layout: load_context.add_labeled_asset("layout".to_owned(), layout),
+            frame_durations: file.frame_durations,
+            tags: file.tags,
+        })
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["aseprite", "ase"]
+    }
+}
+
+struct LayerInfo {
+    name: String,
+    flags: u16,
+    kind: u16,
+    opacity: u8,
+}
+
+impl LayerInfo {
+    fn is_drawn(&self) -> bool {
+        self.flags & LAYER_VISIBLE != 0 && self.kind == NORMAL_LAYER
+    }
+}
+
+struct Cel {
+    x: i32,
+    y: i32,
+    opacity: u8,
+    width: usize,
+    height: usize,
+    /// RGBA pixels
+    pixels: Vec<u8>,
+}
+
+/// The parts of an Aseprite file that the game uses
+struct AsepriteFile {
+    width: usize,
+    height: usize,
+    layers: Vec<LayerInfo>,
+    frame_durations: Vec<Duration>,
+    /// Cels by frame and layer index
+    cels: HashMap<(usize, usize), Cel>,
+    tags: Vec<AsepriteTag>,
+}
+
+impl AsepriteFile {
+    fn parse(bytes: &[u8]) -> Result<AsepriteFile, AsepriteError> {
+        let mut header = ByteReader::new(bytes);
+        header.skip(4)?;
+        if header.u16()? != HEADER_MAGIC {
+            return Err(AsepriteError::InvalidMagic);
+        }
+        let frames = header.u16()? as usize;
+        let width = header.u16()? as usize;
+        let height = header.u16()? as usize;
+        let color_depth = header.u16()?;
+        header.skip(14)?;
+        let transparent_index = header.u8()?;
+        if !matches!(color_depth, 32 | 16 | 8) {
+            return Err(AsepriteError::UnsupportedColorDepth(color_depth));
+        }
+
+        let mut file = AsepriteFile {
+            width,
+            height,
+            layers: vec![],
+            frame_durations: vec![],
+            cels: HashMap::new(),
+            tags: vec![],
+        };
+        let mut palette = vec![[0; 4]; 256];
+        let mut linked_cels = vec![];
+        let mut reader = ByteReader::new(bytes);
+        reader.skip(HEADER_SIZE)?;
+        for frame in 0..frames {
+            let frame_start = reader.position;
+            let frame_size = reader.u32()? as usize;
+            if reader.u16()? != FRAME_MAGIC {
+                return Err(AsepriteError::InvalidMagic);
+            }
+            let old_chunks = reader.u16()? as usize;
+            file.frame_durations
+                .push(Duration::from_millis(reader.u16()? as u64));
+            reader.skip(2)?;
+            let chunks = match reader.u32()? as usize {
+                0 => old_chunks,
+                chunks => chunks,
+            };
+
+            for _ in 0..chunks {
+                let chunk_start = reader.position;
+                let chunk_size = reader.u32()? as usize;
+                let chunk_type = reader.u16()?;
+                let mut chunk = ByteReader::new(reader.bytes(chunk_size.saturating_sub(6))?);
+                match chunk_type {
+                    LAYER_CHUNK => {
+                        let flags = chunk.u16()?;
+                        let kind = chunk.u16()?;
+                        chunk.skip(8)?;
+                        let opacity = chunk.u8()?;
+                        chunk.skip(3)?;
+                        file.layers.push(LayerInfo {
+                            flags,
+                            kind,
+                            opacity,
+                            name: chunk.string()?,
+                        });
+                    }
+                    CEL_CHUNK => {
+                        let layer = chunk.u16()? as usize;
+                        let x = chunk.i16()? as i32;
+                        let y = chunk.i16()? as i32;
+                        let opacity = chunk.u8()?;
+                        let cel_type = chunk.u16()?;
+                        chunk.skip(7)?;
+                        match cel_type {
+                            0 | 2 => {
+                                let width = chunk.u16()? as usize;
+                                let height = chunk.u16()? as usize;
+                                let rest = chunk.rest();
+                                let raw = if cel_type == 2 {
+                                    miniz_oxide::inflate::decompress_to_vec_zlib(rest)
+                                        .map_err(|error| AsepriteError::Decompress(error.status))?
+                                } else {
+                                    rest.to_vec()
+                                };
+                                let pixels =
+                                    to_rgba(&raw, color_depth, &palette, transparent_index);
+                                if pixels.len() < width * height * 4 {
+                                    return Err(AsepriteError::UnexpectedEnd);
+                                }
+                                file.cels.insert(
+                                    (frame, layer),
+                                    Cel {
+                                        x,
+                                        y,
+                                        opacity,
+                                        width,
+                                        height,
+                                        pixels,
+                                    },
+                                );
+                            }
+                            1 => linked_cels.push((frame, layer, chunk.u16()? as usize)),
+                            // tilemap cels are not supported
+                            _ => (),
+                        }
+                    }
+                    TAGS_CHUNK => {
+                        let tags = chunk.u16()?;
+                        chunk.skip(8)?;
+                        for _ in 0..tags {
+                            let first = chunk.u16()? as usize;
+                            let last = chunk.u16()? as usize;
+                            chunk.skip(13)?;
+                            file.tags.push(AsepriteTag {
+                                first,
+                                last,
+                                name: chunk.string()?,
+                            });
+                        }
+                    }
+                    PALETTE_CHUNK => {
+                        let size = chunk.u32()? as usize;
+                        let first = chunk.u32()? as usize;
+                        let last = chunk.u32()? as usize;
+                        chunk.skip(8)?;
+                        palette.resize(size.max(last + 1).max(palette.len()), [0; 4]);
+                        let entries = palette
+                            .get_mut(first..=last)
+                            .ok_or(AsepriteError::UnexpectedEnd)?;
+                        for entry in entries {
+                            let flags = chunk.u16()?;
+                            *entry = [chunk.u8()?, chunk.u8()?, chunk.u8()?, chunk.u8()?];
+                            if flags & 1 != 0 {
+                                chunk.string()?;
+                            }
+                        }
+                    }
+                    OLD_PALETTE_CHUNK if frame == 0 => {
+                        let packets = chunk.u16()?;
+                        let mut entry = 0;
+                        for _ in 0..packets {
+                            entry += chunk.u8()? as usize;
+                            let colors = match chunk.u8()? {
+                                0 => 256,
+                                colors => colors as usize,
+                            };
+                            for _ in 0..colors {
+                                if entry < palette.len() {
+                                    palette[entry] = [chunk.u8()?, chunk.u8()?, chunk.u8()?, 255];
+                                }
+                                entry += 1;
+                            }
+                        }
+                    }
+                    _ => (),
+                }
+                reader.position = chunk_start + chunk_size;
+            }
+            reader.position = frame_start + frame_size;
+        }
+
+        for (frame, layer, linked) in linked_cels {
+            let cel = file
+                .cels
+                .get(&(linked, layer))
+                .ok_or(AsepriteError::MissingLinkedCel { frame, linked })?;
+            let cel = Cel {
+                pixels: cel.pixels.clone(),
+                ..*cel
+            };
+            file.cels.insert((frame, layer), cel);
+        }
+
+        Ok(file)
+    }
+
+    /// Blends the cels of all matching layers in the given frame into RGBA pixels of the canvas size
+    fn canvas(&self, frame: usize, include_layer: impl Fn(usize) -> bool) -> Vec<u8> {
+        let mut canvas = vec![0; self.width * self.height * 4];
+        for (index, layer) in self.layers.iter().enumerate() {
+            if !include_layer(index) {
+                continue;
+            }
+            let Some(cel) = self.cels.get(&(frame, index)) else {
+                continue;
+            };
+            let opacity = cel.opacity as u32 * layer.opacity as u32 / 255;
+            for row in 0..cel.height {
+                for column in 0..cel.width {
+                    let (x, y) = (cel.x + column as i32, cel.y + row as i32);
+                    // cels can reach outside of the canvas
+                    if x < 0 || y < 0 || x as usize >= self.width || y as usize >= self.height {
+                        continue;
+                    }
+                    let source = (row * cel.width + column) * 4;
+                    let target = (y as usize * self.width + x as usize) * 4;
+                    blend(
+                        &mut canvas[target..target + 4],
+                        &cel.pixels[source..source + 4],
+                        opacity,
+                    );
+                }
+            }
+        }
+
+        canvas
+    }
+
+    fn image(&self, pixels: Vec<u8>) -> Image {
+        Image::new(
+            Extent3d {
+                width: self.width as u32,
+                height: self.height as u32,
+                depth_or_array_layers: 1,
+            },
+            TextureDimension::D2,
+            pixels,
+            TextureFormat::Rgba8UnormSrgb,
+            RenderAssetUsages::default(),
+        )
+    }
+}
+
+/// Draws `source` over `target` with normal blending
+fn blend(target: &mut [u8], source: &[u8], opacity: u32) {
+    let alpha = source[3] as u32 * opacity / 255;
+    if alpha == 0 {
+        return;
+    }
+    let target_alpha = target[3] as u32 * (255 - alpha) / 255;
+    let result_alpha = alpha + target_alpha;
+    for channel in 0..3 {
+        target[channel] = ((source[channel] as u32 * alpha + target[channel] as u32 * target_alpha)
+            / result_alpha) as u8;
+    }
+    target[3] = result_alpha as u8;
+}
+
+fn to_rgba(raw: &[u8], color_depth: u16, palette: &[[u8; 4]], transparent_index: u8) -> Vec<u8> {
+    match color_depth {
+        32 => raw.to_vec(),
+        16 => raw
+            .chunks_exact(2)
+            .flat_map(|pixel| [pixel[0], pixel[0], pixel[0], pixel[1]])
+            .collect(),
+        _ => raw
+            .iter()
+            .flat_map(|index| {
+                if *index == transparent_index {
+                    [0; 4]
+                } else {
+                    palette.get(*index as usize).copied().unwrap_or([0; 4])
+                }
+            })
+            .collect(),
+    }
+}
+
+struct ByteReader<'a> {
+    bytes: &'a [u8],
+    position: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    fn new(bytes: &'a [u8]) -> Self {
+        ByteReader { bytes, position: 0 }
+    }
+
+    fn bytes(&mut self, count: usize) -> Result<&'a [u8], AsepriteError> {
+        let bytes = self
+            .bytes
+            .get(self.position..self.position + count)
+            .ok_or(AsepriteError::UnexpectedEnd)?;
+        self.position += count;
+        Ok(bytes)
+    }
+
+    fn rest(&mut self) -> &'a [u8] {
+        let rest = &self.bytes[self.position.min(self.bytes.len())..];
+        self.position = self.bytes.len();
+        rest
+    }
+
+    fn skip(&mut self, count: usize) -> Result<(), AsepriteError> {
+        self.bytes(count).map(|_| ())
+    }
+
+    fn u8(&mut self) -> Result<u8, AsepriteError> {
+        Ok(self.bytes(1)?[0])
+    }
+
+    fn u16(&mut self) -> Result<u16, AsepriteError> {
+        let bytes = self.bytes(2)?;
+        Ok(u16::from_le_bytes([bytes[0], bytes[1]]))
+    }
+
+    fn i16(&mut self) -> Result<i16, AsepriteError> {
+        Ok(self.u16()? as i16)
+    }
+
+    fn u32(&mut self) -> Result<u32, AsepriteError> {
+        let bytes = self.bytes(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String, AsepriteError> {
+        let length = self.u16()? as usize;
+        Ok(String::from_utf8_lossy(self.bytes(length)?).into_owned())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn string(text: &str) -> Vec<u8> {
+        let mut bytes = (text.len() as u16).to_le_bytes().to_vec();
+        bytes.extend(text.as_bytes());
+        bytes
+    }
+
+    fn chunk(kind: u16, data: &[u8]) -> Vec<u8> {
+        let mut bytes = ((data.len() + 6) as u32).to_le_bytes().to_vec();
+        bytes.extend(kind.to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn layer(name: &str, flags: u16) -> Vec<u8> {
+        let mut data = flags.to_le_bytes().to_vec();
+        data.extend(NORMAL_LAYER.to_le_bytes());
+        data.extend([0; 8]);
+        data.push(255);
+        data.extend([0; 3]);
+        data.extend(string(name));
+        chunk(LAYER_CHUNK, &data)
+    }
+
+    fn cel_header(layer: u16, position: (i16, i16), cel_type: u16) -> Vec<u8> {
+        let mut data = layer.to_le_bytes().to_vec();
+        data.extend(position.0.to_le_bytes());
+        data.extend(position.1.to_le_bytes());
+        data.push(255);
+        data.extend(cel_type.to_le_bytes());
+        data.extend([0; 7]);
+        data
+    }
+
+    /// Cel of RGBA pixels; compressed like Aseprite saves them or raw
+    fn cel(
+        layer: u16,
+        position: (i16, i16),
+        size: (u16, u16),
+        pixels: &[u8],
+        compressed: bool,
+    ) -> Vec<u8> {
+        let mut data = cel_header(layer, position, if compressed { 2 } else { 0 });
+        data.extend(size.0.to_le_bytes());
+        data.extend(size.1.to_le_bytes());
+        if compressed {
+            data.extend(miniz_oxide::deflate::compress_to_vec_zlib(pixels, 6));
+        } else {
+            data.extend(pixels);
+        }
+        chunk(CEL_CHUNK, &data)
+    }
+
+    fn linked_cel(layer: u16, frame: u16) -> Vec<u8> {
+        let mut data = cel_header(layer, (0, 0), 1);
+        data.extend(frame.to_le_bytes());
+        chunk(CEL_CHUNK, &data)
+    }
+
+    fn tags(tags: &[(u16, u16, &str)]) -> Vec<u8> {
+        let mut data = (tags.len() as u16).to_le_bytes().to_vec();
+        data.extend([0; 8]);
+        for &(first, last, name) in tags {
+            data.extend(first.to_le_bytes());
+            data.extend(last.to_le_bytes());
+            data.extend([0; 13]);
+            data.extend(string(name));
+        }
+        chunk(TAGS_CHUNK, &data)
+    }
+
+    fn frame(duration: u16, chunks: &[Vec<u8>]) -> Vec<u8> {
+        let data = chunks.concat();
+        let mut bytes = ((data.len() + 16) as u32).to_le_bytes().to_vec();
+        bytes.extend(FRAME_MAGIC.to_le_bytes());
+        bytes.extend((chunks.len() as u16).to_le_bytes());
+        bytes.extend(duration.to_le_bytes());
+        bytes.extend([0; 2]);
+        bytes.extend((chunks.len() as u32).to_le_bytes());
+        bytes.extend(data);
+        bytes
+    }
+
+    fn file(size: (u16, u16), color_depth: u16, frames: &[Vec<u8>]) -> Vec<u8> {
+        let data = frames.concat();
+        let mut bytes = ((data.len() + HEADER_SIZE) as u32).to_le_bytes().to_vec();
+        bytes.extend(HEADER_MAGIC.to_le_bytes());
+        bytes.extend((frames.len() as u16).to_le_bytes());
+        bytes.extend(size.0.to_le_bytes());
+        bytes.extend(size.1.to_le_bytes());
+        bytes.extend(color_depth.to_le_bytes());
+        bytes.resize(HEADER_SIZE, 0);
+        bytes.extend(data);
+        bytes
+    }
+
+    const RED: [u8; 4] = [255, 0, 0, 255];
+    const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+    #[test]
+    fn parses_header_and_layers() {
+        let bytes = file(
+            (3, 2),
+            32,
+            &[
+                frame(100, &[layer("Ground", LAYER_VISIBLE), layer("Notes", 0)]),
+                frame(250, &[]),
+            ],
+        );
+        let file = AsepriteFile::parse(&bytes).unwrap();
+
+        assert_eq!((file.width, file.height), (3, 2));
+        assert_eq!(
+            file.frame_durations,
+            [Duration::from_millis(100), Duration::from_millis(250)]
+        );
+        let names: Vec<_> = file
+            .layers
+            .iter()
+            .map(|layer| layer.name.as_str())
+            .collect();
+        assert_eq!(names, ["Ground", "Notes"]);
+        assert!(file.layers[0].is_drawn());
+        assert!(!file.layers[1].is_drawn());
+    }
+
+    #[test]
+    fn rejects_invalid_headers() {
+        let mut bytes = file((1, 1), 32, &[frame(100, &[])]);
+        assert!(matches!(
+            AsepriteFile::parse(&bytes[..HEADER_SIZE - 1]),
+            Err(AsepriteError::UnexpectedEnd)
+        ));
+        assert!(matches!(
+            AsepriteFile::parse(&file((1, 1), 24, &[])),
+            Err(AsepriteError::UnsupportedColorDepth(24))
+        ));
+        bytes[4] = 0;
+        assert!(matches!(
+            AsepriteFile::parse(&bytes),
+            Err(AsepriteError::InvalidMagic)
+        ));
+    }
+
+    #[test]
+    fn blends_cels_into_the_canvas() {
+        let bytes = file(
+            (2, 2),
+            32,
+            &[
+                frame(
+                    100,
+                    &[
+                        layer("Back", LAYER_VISIBLE),
+                        layer("Front", LAYER_VISIBLE),
+                        cel(0, (0, 0), (2, 1), &[RED, RED].concat(), false),
+                        // reaches past the right edge of the canvas
+                        cel(1, (1, 0), (2, 2), &[BLUE; 4].concat(), true),
+                    ],
+                ),
+                frame(100, &[linked_cel(0, 0)]),
+            ],
+        );
+        let file = AsepriteFile::parse(&bytes).unwrap();
+
+        let canvas = file.canvas(0, |_| true);
+        assert_eq!(canvas, [RED, BLUE, [0; 4], BLUE].concat());
+        let back = file.canvas(0, |layer| layer == 0);
+        assert_eq!(back, [RED, RED, [0; 4], [0; 4]].concat());
+        // the linked cel shows the cel of the first frame
+        assert_eq!(file.canvas(1, |_| true), back);
+    }
+
+    #[test]
+    fn reports_missing_linked_cels() {
+        let bytes = file(
+            (1, 1),
+            32,
+            &[frame(
+                100,
+                &[layer("Layer", LAYER_VISIBLE), linked_cel(0, 3)],
+            )],
+        );
+        assert!(matches!(
+            AsepriteFile::parse(&bytes),
+            Err(AsepriteError::MissingLinkedCel {
+                frame: 0,
+                linked: 3
+            })
+        ));
+    }
+
+    #[test]
+    fn tags_are_animations() {
+        let bytes = file(
+            (1, 1),
+            32,
+            &[
+                frame(
+                    150,
+                    &[tags(&[
+                        (0, 1, "walk"),
+                        (1, 2, "outside"),
+                        (1, 0, "reversed"),
+                    ])],
+                ),
+                frame(300, &[]),
+            ],
+        );
+        let file = AsepriteFile::parse(&bytes).unwrap();
+        let tag_names: Vec<_> = file.tags.iter().map(|tag| tag.name.as_str()).collect();
+        assert_eq!(tag_names, ["walk", "outside", "reversed"]);
+
+        let aseprite = Aseprite {
+            layers: vec![],
+            atlas: Handle::default(),
+            layout: Handle::default(),
+            frame_durations: file.frame_durations,
+            tags: file.tags,
+        };
+        let (indices, duration) = aseprite.animation("walk").unwrap();
+        assert_eq!((indices.first, indices.last), (0, 1));
+        assert_eq!(duration, Duration::from_millis(150));
+        // tags can point past the frames of a broken file
+        assert!(aseprite.animation("outside").is_none());
+        assert!(aseprite.animation("reversed").is_none());
+        assert!(aseprite.animation("run").is_none());
+    }
+
+    #[test]
+    fn parses_the_resources() {
+        let map = AsepriteFile::parse(&std::fs::read("resources/map.aseprite").unwrap()).unwrap();
+        assert_eq!((map.width, map.height), (23, 48));
+        let characters =
+            AsepriteFile::parse(&std::fs::read("resources/characters.aseprite").unwrap()).unwrap();
+        let player = characters.tags.iter().find(|tag| tag.name == "player");
+        // the same frames the player uses from `tilemap-characters.png`
+        assert_eq!(player.map(|tag| (tag.first, tag.last)), Some((4, 5)));
+    }
+}
@@ -0,0 +1,90 @@
+use crate::grid::MapGrid;
+use crate::palette::TileKind;
+
+/// Columns of `tilemap.png`
+const TILEMAP_COLUMNS: usize = 20;
+
+/// Picks the atlas index of a tile from its neighbours. Ground uses the 16 tile set in the top left
+/// of `tilemap.png`: the row depends on whether there is ground above and below, the column on
+/// whether there is ground to the left and right. Other kinds always use the same tile.
+pub fn atlas_index(grid: &MapGrid, x: usize, y: usize, kind: TileKind) -> Option<usize> {
+    if kind != TileKind::Ground {
+        return kind.atlas_index();
+    }
+    let connects = |dx: isize, dy: isize| grid.connects(x as isize + dx, y as isize + dy, kind);
+
+    let row = match (connects(0, -1), connects(0, 1)) {
+        // grass on top
+        (false, true) => 0,
+        (false, false) => 1,
+        (true, true) => 6,
+        (true, false) => 7,
+    };
+    let column = match (connects(-1, 0), connects(1, 0)) {
+        (false, false) => 0,
+        (false, true) => 1,
+        (true, true) => 2,
+        (true, false) => 3,
+    };
+    Some(row * TILEMAP_COLUMNS + column)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Grid of ground (`#`) and platforms (`=`)
+    fn grid(rows: &[&str]) -> MapGrid {
+        let cells = rows
+            .iter()
+            .flat_map(|row| row.chars())
+            .map(|char| match char {
+                '#' => Some(TileKind::Ground),
+                '=' => Some(TileKind::Platform),
+                _ => None,
+            })
+            .collect();
+        MapGrid {
+            width: rows[0].len(),
+            cells,
+        }
+    }
+
+    #[test]
+    fn picks_tiles_from_neighbours() {
+        let grid = grid(&[
+            ".....", //
+            ".###.", //
+            ".###.", //
+            ".###.", //
+            ".....", //
+            "..#..", //
+            "#..=.", //
+        ]);
+        let index = |x, y| atlas_index(&grid, x, y, grid.cells[y * grid.width + x].unwrap());
+
+        let corners_and_edges = [
+            // top row with grass
+            ((1, 1), 1),
+            ((2, 1), 2),
+            ((3, 1), 3),
+            // sides
+            ((1, 2), 121),
+            ((2, 2), 122),
+            ((3, 2), 123),
+            // bottom row
+            ((1, 3), 141),
+            ((2, 3), 142),
+            ((3, 3), 143),
+        ];
+        for ((x, y), expected) in corners_and_edges {
+            assert_eq!(index(x, y), Some(expected), "({x}, {y})");
+        }
+        // isolated
+        assert_eq!(index(2, 5), Some(20));
+        // ground continues past the left and bottom edge of the map
+        assert_eq!(index(0, 6), Some(3));
+        // other kinds are not autotiled
+        assert_eq!(index(3, 6), TileKind::Platform.atlas_index());
+    }
+}
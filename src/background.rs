@@ -0,0 +1,182 @@
+use crate::camera::{follow_player, FollowCamera, RESOLUTION};
+use crate::loading::ImageAssets;
+use bevy::prelude::*;
+use bevy::transform::TransformSystem;
+
+pub struct BackgroundPlugin;
+
+impl Plugin for BackgroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ParallaxBackground>()
+            .add_systems(
+                Update,
+                spawn_background.run_if(resource_exists::<ImageAssets>),
+            )
+            .add_systems(
+                PostUpdate,
+                scroll_background
+                    .after(follow_player)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Size of the tiles in `tilemap-backgrounds.png`
+const BACKGROUND_TILE_SIZE: f32 = 24.;
+
+/// Layers drawn behind the map, from back to front; replace the resource to change the scene
+#[derive(Resource, Clone, Debug)]
+pub struct ParallaxBackground {
+    pub layers: Vec<ParallaxLayer>,
+}
+
+#[derive(Clone, Debug)]
+pub struct ParallaxLayer {
+    /// Indices in `tilemap-backgrounds.png` row by row; all rows have the same length and are
+    /// repeated horizontally
+    pub tiles: Vec<Vec<usize>>,
+    /// Repeat the last row down to the bottom of the screen
+    pub fill_down: bool,
+    /// Top left corner of the tiles; relative to the camera for a scroll factor of 0
+    pub position: Vec2,
+    /// How much the layer moves with the camera: 0 stays in place on the screen, 1 moves with the map
+    pub scroll: Vec2,
+    /// Automatic movement in pixels per second, e.g. for drifting clouds
+    pub velocity: Vec2,
+    pub z: f32,
+}
+
+impl Default for ParallaxBackground {
+    /// Sky with drifting clouds and hills in front of it
+    fn default() -> Self {
+        ParallaxBackground {
+            layers: vec![
+                ParallaxLayer {
+                    tiles: vec![vec![0]],
+                    fill_down: true,
+                    position: Vec2::new(0., RESOLUTION.y as f32 / 2.),
+                    scroll: Vec2::ZERO,
+                    velocity: Vec2::ZERO,
+                    z: -30.,
+                },
+                ParallaxLayer {
+                    tiles: vec![vec![8, 10], vec![16, 16]],
+                    fill_down: true,
+                    position: Vec2::new(0., 40.),
+                    scroll: Vec2::splat(0.1),
+                    velocity: Vec2::new(-6., 0.),
+                    z: -20.,
+                },
+                ParallaxLayer {
+                    tiles: vec![vec![9, 11], vec![17, 17]],
+                    fill_down: true,
+                    position: Vec2::new(0., -20.),
+                    scroll: Vec2::splat(0.4),
+                    velocity: Vec2::ZERO,
+                    z: -10.,
+                },
+            ],
+        }
+    }
+}
+
+impl ParallaxLayer {
+    fn width(&self) -> f32 {
+        self.tiles.first().map_or(0, Vec::len) as f32 * BACKGROUND_TILE_SIZE
+    }
+}
+
+#[derive(Component)]
+struct BackgroundLayer {
+    layer: ParallaxLayer,
+    /// Distance moved with the layer's velocity
+    offset: Vec2,
+}
+
+fn spawn_background(
+    mut commands: Commands,
+    assets: Res<ImageAssets>,
+    background: Res<ParallaxBackground>,
+    layers: Query<Entity, With<BackgroundLayer>>,
+) {
+    if !background.is_changed() {
+        return;
+    }
+    for entity in &layers {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let view = RESOLUTION.as_vec2();
+    for layer in &background.layers {
+        if layer.width() == 0. {
+            continue;
+        }
+        // cover the view no matter where in the pattern it starts
+        let columns = ((view.x + layer.width()) / BACKGROUND_TILE_SIZE).ceil() as usize;
+        let fill_rows = if layer.fill_down {
+            (view.y / BACKGROUND_TILE_SIZE).ceil() as usize + 1
+        } else {
+            0
+        };
+        let last_row = layer.tiles.last().unwrap();
+        let rows = layer
+            .tiles
+            .iter()
+            .chain(std::iter::repeat_n(last_row, fill_rows));
+
+        commands
+            .spawn((
+                SpatialBundle::default(),
+                BackgroundLayer {
+                    layer: layer.clone(),
+                    offset: Vec2::ZERO,
+                },
+            ))
+            .with_children(|parent| {
+                for (row, tiles) in rows.enumerate() {
+                    for column in 0..columns {
+                        parent.spawn((
+                            SpriteBundle {
+                                texture: assets.tilemap_backgrounds.clone(),
+                                transform: Transform::from_xyz(
+                                    (column as f32 + 0.5) * BACKGROUND_TILE_SIZE,
+                                    -(row as f32 + 0.5) * BACKGROUND_TILE_SIZE,
+                                    0.,
+                                ),
+                                ..default()
+                            },
+                            TextureAtlas {
+                                layout: assets.tilemap_backgrounds_layout.clone(),
+                                index: tiles[column % tiles.len()],
+                            },
+                        ));
+                    }
+                }
+            });
+    }
+}
+
+fn scroll_background(
+    time: Res<Time>,
+    camera: Query<&Transform, With<FollowCamera>>,
+    mut layers: Query<(&mut Transform, &mut BackgroundLayer), Without<FollowCamera>>,
+) {
+    let Ok(camera) = camera.get_single() else {
+        return;
+    };
+    let camera = camera.translation.truncate();
+    let view_left = camera.x - RESOLUTION.x as f32 / 2.;
+
+    for (mut transform, mut background) in &mut layers {
+        let width = background.layer.width();
+        let velocity = background.layer.velocity;
+        background.offset += velocity * time.delta_seconds();
+        background.offset.x = background.offset.x.rem_euclid(width);
+
+        let layer = &background.layer;
+        let start = layer.position + camera * (Vec2::ONE - layer.scroll) + background.offset;
+        // the pattern repeats, so move it back to the first repetition visible on the left
+        let x = view_left - (view_left - start.x).rem_euclid(width);
+        transform.translation = Vec3::new(x, start.y, layer.z);
+    }
+}
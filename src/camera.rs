@@ -0,0 +1,305 @@
+use crate::loading::TILE_SIZE;
+use crate::map::{MapTile, ShipTile};
+use crate::player::Player;
+use crate::rng::{GameRng, RngStream};
+use crate::{HEIGHT, WIDTH};
+use avian2d::prelude::*;
+use bevy::ecs::query::QueryFilter;
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_resource::{
+    Extent3d, TextureDescriptor, TextureDimension, TextureFormat, TextureUsages,
+};
+use bevy::render::view::RenderLayers;
+use bevy::transform::TransformSystem;
+use bevy::window::PrimaryWindow;
+use rand::Rng;
+
+pub struct CameraPlugin;
+
+impl Plugin for CameraPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<MapBounds>()
+            .init_resource::<TerrainBounds>()
+            .init_resource::<PixelScaling>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(
+                Update,
+                (update_map_bounds, toggle_scaling, scale_to_window).chain(),
+            )
+            .add_systems(
+                PostUpdate,
+                follow_player
+                    .after(PhysicsSet::Sync)
+                    .before(TransformSystem::TransformPropagate),
+            );
+    }
+}
+
+/// Camera that smoothly follows the player
+#[derive(Component)]
+pub struct FollowCamera {
+    /// The camera only moves once the player leaves this area around its focus
+    pub deadzone: Vec2,
+    /// How far the camera looks ahead in the direction the player is moving
+    pub look_ahead: f32,
+    /// How quickly the camera catches up; higher is faster
+    pub smoothing: f32,
+    /// Strength of the camera shake between 0 and 1; wears off over time
+    pub trauma: f32,
+    focus: Vec2,
+    look_ahead_offset: f32,
+}
+
+impl Default for FollowCamera {
+    fn default() -> Self {
+        FollowCamera {
+            deadzone: Vec2::new(TILE_SIZE * 2., TILE_SIZE * 1.5),
+            look_ahead: TILE_SIZE * 3.,
+            smoothing: 5.,
+            trauma: 0.,
+            focus: Vec2::ZERO,
+            look_ahead_offset: 0.,
+        }
+    }
+}
+
+/// World space area covered by the map and the ship, which can lift off;
+/// the camera does not show anything outside of it
+#[derive(Resource, Default)]
+pub struct MapBounds(pub Option<Rect>);
+
+/// Area covered by the map tiles alone, which only changes when the map is rebuilt
+#[derive(Resource, Default)]
+struct TerrainBounds(Option<Rect>);
+
+/// How the low resolution image of the world is scaled up to the window;
+/// switched in the menu or with F2
+///
+/// The choice is not saved, every session starts pixel perfect.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PixelScaling {
+    /// Largest whole number scale that fits the window; every pixel has the same size
+    #[default]
+    PixelPerfect,
+    /// Fill as much of the window as possible
+    Fit,
+}
+
+impl PixelScaling {
+    pub const KEY: KeyCode = KeyCode::F2;
+
+    pub fn toggled(self) -> PixelScaling {
+        match self {
+            PixelScaling::PixelPerfect => PixelScaling::Fit,
+            PixelScaling::Fit => PixelScaling::PixelPerfect,
+        }
+    }
+}
+
+/// Size of the image the world is rendered to, before it is scaled up to the window
+pub(crate) const RESOLUTION: UVec2 = UVec2::new(WIDTH as u32 / 2, HEIGHT as u32 / 2);
+/// Render layer of the scaled up world image; the world itself is on the default layer 0
+const WINDOW_LAYER: usize = 1;
+/// Minimum horizontal speed of the player for the camera to look ahead
+const LOOK_AHEAD_SPEED: f32 = 10.;
+/// Offset in pixels of the strongest camera shake
+const MAX_SHAKE: f32 = 4.;
+/// Trauma that wears off per second
+const TRAUMA_DECAY: f32 = 1.5;
+
+/// Sprite showing the rendered world in the window
+#[derive(Component)]
+struct WorldImage;
+
+fn spawn_camera(mut commands: Commands, mut images: ResMut<Assets<Image>>) {
+    let size = Extent3d {
+        width: RESOLUTION.x,
+        height: RESOLUTION.y,
+        ..default()
+    };
+    let mut image = Image {
+        texture_descriptor: TextureDescriptor {
+            label: Some("world"),
+            size,
+            dimension: TextureDimension::D2,
+            format: TextureFormat::Bgra8UnormSrgb,
+            mip_level_count: 1,
+            sample_count: 1,
+            usage: TextureUsages::TEXTURE_BINDING
+                | TextureUsages::COPY_DST
+                | TextureUsages::RENDER_ATTACHMENT,
+            view_formats: &[],
+        },
+        ..default()
+    };
+    // fill with zeros
+    image.resize(size);
+    let world_image = images.add(image);
+
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                order: -1,
+                target: RenderTarget::Image(world_image.clone()),
+                ..default()
+            },
+            ..default()
+        },
+        FollowCamera::default(),
+    ));
+    commands.spawn((
+        SpriteBundle {
+            texture: world_image,
+            ..default()
+        },
+        WorldImage,
+        RenderLayers::layer(WINDOW_LAYER),
+    ));
+    // also renders the UI in full resolution
+    commands.spawn((
+        Camera2dBundle {
+            camera: Camera {
+                clear_color: ClearColorConfig::Custom(Color::BLACK),
+                ..default()
+            },
+            ..default()
+        },
+        IsDefaultUiCamera,
+        RenderLayers::layer(WINDOW_LAYER),
+    ));
+}
+
+fn toggle_scaling(keyboard: Res<ButtonInput<KeyCode>>, mut scaling: ResMut<PixelScaling>) {
+    if keyboard.just_pressed(PixelScaling::KEY) {
+        *scaling = scaling.toggled();
+        info!("Scaling the window with {:?}", *scaling);
+    }
+}
+
+/// Scales the world image up to the window; the remaining space is black
+fn scale_to_window(
+    scaling: Res<PixelScaling>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut world_image: Query<&mut Transform, With<WorldImage>>,
+) {
+    let (Ok(window), Ok(mut transform)) = (windows.get_single(), world_image.get_single_mut())
+    else {
+        return;
+    };
+    let fit = (window.size() / RESOLUTION.as_vec2()).min_element();
+    let scale = match *scaling {
+        PixelScaling::Fit => fit,
+        PixelScaling::PixelPerfect => fit.floor().max(1.),
+    };
+    if transform.scale.x != scale {
+        transform.scale = Vec3::new(scale, scale, 1.);
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn update_map_bounds(
+    added_map_tiles: Query<(), Added<MapTile>>,
+    changed_ship_tiles: Query<(), (With<ShipTile>, Or<(Added<ShipTile>, Changed<Transform>)>)>,
+    mut removed_map_tiles: RemovedComponents<MapTile>,
+    mut removed_ship_tiles: RemovedComponents<ShipTile>,
+    map_tiles: Query<&Transform, With<MapTile>>,
+    ship_tiles: Query<&Transform, With<ShipTile>>,
+    mut terrain_bounds: ResMut<TerrainBounds>,
+    mut bounds: ResMut<MapBounds>,
+) {
+    let map_changed = !added_map_tiles.is_empty() || removed_map_tiles.read().count() > 0;
+    let ship_changed = !changed_ship_tiles.is_empty() || removed_ship_tiles.read().count() > 0;
+    if !map_changed && !ship_changed {
+        return;
+    }
+
+    if map_changed {
+        terrain_bounds.0 = tile_bounds(&map_tiles);
+    }
+    // the ship moves every frame while it lifts off
+    bounds.0 = match (terrain_bounds.0, tile_bounds(&ship_tiles)) {
+        (Some(terrain), Some(ship)) => Some(terrain.union(ship)),
+        (terrain, ship) => terrain.or(ship),
+    };
+}
+
+fn tile_bounds<F: QueryFilter>(tiles: &Query<&Transform, F>) -> Option<Rect> {
+    tiles
+        .iter()
+        .map(|transform| {
+            Rect::from_center_size(
+                transform.translation.truncate(),
+                Vec2::splat(TILE_SIZE) * transform.scale.truncate(),
+            )
+        })
+        .reduce(|bounds, tile| bounds.union(tile))
+}
+
+#[allow(clippy::type_complexity)]
+pub(crate) fn follow_player(
+    time: Res<Time>,
+    bounds: Res<MapBounds>,
+    mut rng: ResMut<GameRng>,
+    player: Query<(&Transform, Option<&LinearVelocity>, Ref<Player>), Without<FollowCamera>>,
+    mut camera: Query<(&mut Transform, &mut FollowCamera, &OrthographicProjection)>,
+) {
+    let Ok((player_transform, velocity, player)) = player.get_single() else {
+        return;
+    };
+    let Ok((mut transform, mut camera, projection)) = camera.get_single_mut() else {
+        return;
+    };
+    let position = player_transform.translation.truncate();
+
+    let target = if player.is_added() {
+        // jump to newly spawned players instead of panning through the level
+        camera.focus = position;
+        camera.look_ahead_offset = 0.;
+        position
+    } else {
+        let offset = position - camera.focus;
+        let deadzone = camera.deadzone;
+        camera.focus += offset - offset.clamp(-deadzone, deadzone);
+
+        let speed = velocity.map_or(0., |velocity| velocity.x);
+        // the camera centers on the player again once it stops
+        let desired_look_ahead = if speed.abs() > LOOK_AHEAD_SPEED {
+            camera.look_ahead * speed.signum()
+        } else {
+            0.
+        };
+        let blend = 1. - (-camera.smoothing * time.delta_seconds()).exp();
+        camera.look_ahead_offset = camera.look_ahead_offset.lerp(desired_look_ahead, blend);
+
+        let target = camera.focus + Vec2::X * camera.look_ahead_offset;
+        transform.translation.truncate().lerp(target, blend)
+    };
+
+    let mut target = match bounds.0 {
+        Some(bounds) => clamp_to_bounds(target, projection.area.half_size(), bounds),
+        None => target,
+    };
+    if camera.trauma > 0. {
+        let rng = rng.stream(RngStream::Effects);
+        let direction = Vec2::new(rng.gen_range(-1. ..1.), rng.gen_range(-1. ..1.));
+        target += direction * MAX_SHAKE * camera.trauma * camera.trauma;
+        camera.trauma = (camera.trauma - TRAUMA_DECAY * time.delta_seconds()).max(0.);
+    }
+    transform.translation = target.extend(transform.translation.z);
+}
+
+/// Keeps the view inside of the bounds; centers it if the bounds are smaller than the view
+fn clamp_to_bounds(center: Vec2, half_view: Vec2, bounds: Rect) -> Vec2 {
+    let clamp_axis = |center: f32, half_view: f32, min: f32, max: f32| {
+        if max - min <= half_view * 2. {
+            (min + max) / 2.
+        } else {
+            center.clamp(min + half_view, max - half_view)
+        }
+    };
+    Vec2::new(
+        clamp_axis(center.x, half_view.x, bounds.min.x, bounds.max.x),
+        clamp_axis(center.y, half_view.y, bounds.min.y, bounds.max.y),
+    )
+}
@@ -0,0 +1,207 @@
+use crate::loading::TILE_SIZE;
+use crate::map::MapOrigin;
+use crate::physics::GameLayer;
+use avian2d::prelude::*;
+use bevy::prelude::*;
+use bevy::utils::HashSet;
+
+pub struct ColliderPlugin;
+
+impl Plugin for ColliderPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, merge_colliders);
+    }
+}
+
+/// Grid cell that blocks the player. Instead of one collider per tile, contiguous cells are merged
+/// into as few rectangles as possible, which avoids seams the character controller can catch on.
+#[derive(Component, Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct StaticCell {
+    pub x: usize,
+    pub y: usize,
+    pub shape: CellShape,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum CellShape {
+    Solid,
+    /// Thin collider at the top of the cell
+    Platform,
+}
+
+/// Collider covering a rectangle of `StaticCell`s; rebuilt whenever cells are spawned or despawned
+#[derive(Component)]
+struct MergedCollider;
+
+/// Height of a platform collider
+const PLATFORM_HEIGHT: f32 = TILE_SIZE / 4.;
+
+fn merge_colliders(
+    mut commands: Commands,
+    added: Query<(), Added<StaticCell>>,
+    mut removed: RemovedComponents<StaticCell>,
+    cells: Query<&StaticCell>,
+    merged: Query<Entity, With<MergedCollider>>,
+    origin: Res<MapOrigin>,
+) {
+    let removed = removed.read().count() > 0;
+    if added.is_empty() && !removed {
+        return;
+    }
+
+    for entity in &merged {
+        commands.entity(entity).despawn();
+    }
+    for CellRect {
+        cell,
+        width,
+        height,
+    } in merge_cells(cells.iter().copied())
+    {
+        let size = Vec2::new(width as f32, height as f32) * TILE_SIZE;
+        let mut center = origin.tile_position(cell.x, cell.y)
+            + Vec3::new(size.x - TILE_SIZE, TILE_SIZE - size.y, 0.) / 2.;
+        let collider = match cell.shape {
+            CellShape::Solid => Collider::rectangle(size.x, size.y),
+            CellShape::Platform => {
+                center.y += (TILE_SIZE - PLATFORM_HEIGHT) / 2.;
+                Collider::rectangle(size.x, PLATFORM_HEIGHT)
+            }
+        };
+        commands.spawn((
+            MergedCollider,
+            TransformBundle::from_transform(Transform::from_translation(center)),
+            RigidBody::Static,
+            collider,
+            CollisionLayers::new(GameLayer::Ground, [GameLayer::Player, GameLayer::Item]),
+        ));
+    }
+}
+
+/// Rectangle of cells with the same shape, extending right and down from `cell`
+#[derive(Debug, PartialEq)]
+struct CellRect {
+    cell: StaticCell,
+    width: usize,
+    height: usize,
+}
+
+/// Greedily covers the cells with rectangles, row by row from the top left
+fn merge_cells(cells: impl Iterator<Item = StaticCell>) -> Vec<CellRect> {
+    let mut remaining: HashSet<StaticCell> = cells.collect();
+    let mut sorted: Vec<StaticCell> = remaining.iter().copied().collect();
+    sorted.sort_by_key(|cell| (cell.y, cell.x));
+
+    let mut rects = vec![];
+    for cell in sorted {
+        if !remaining.remove(&cell) {
+            continue;
+        }
+        let shifted = |dx: usize, dy: usize| StaticCell {
+            x: cell.x + dx,
+            y: cell.y + dy,
+            ..cell
+        };
+
+        let mut width = 1;
+        while remaining.remove(&shifted(width, 0)) {
+            width += 1;
+        }
+        // platforms are only merged into horizontal strips
+        let mut height = 1;
+        while cell.shape == CellShape::Solid
+            && (0..width).all(|dx| remaining.contains(&shifted(dx, height)))
+        {
+            for dx in 0..width {
+                remaining.remove(&shifted(dx, height));
+            }
+            height += 1;
+        }
+        rects.push(CellRect {
+            cell,
+            width,
+            height,
+        });
+    }
+    rects
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Cells drawn as rows of `#` (solid) and `-` (platform)
+    fn cells(rows: &[&str]) -> Vec<StaticCell> {
+        let mut cells = vec![];
+        for (y, row) in rows.iter().enumerate() {
+            for (x, char) in row.chars().enumerate() {
+                let shape = match char {
+                    '#' => CellShape::Solid,
+                    '-' => CellShape::Platform,
+                    _ => continue,
+                };
+                cells.push(StaticCell { x, y, shape });
+            }
+        }
+        cells
+    }
+
+    /// Merges the cells and checks that the rectangles cover each of them exactly once
+    fn merge(rows: &[&str]) -> Vec<(usize, usize, usize, usize)> {
+        let cells = cells(rows);
+        let rects = merge_cells(cells.iter().copied());
+
+        let mut covered = vec![];
+        for rect in &rects {
+            for y in 0..rect.height {
+                for x in 0..rect.width {
+                    covered.push(StaticCell {
+                        x: rect.cell.x + x,
+                        y: rect.cell.y + y,
+                        shape: rect.cell.shape,
+                    });
+                }
+            }
+        }
+        let mut expected = cells;
+        expected.sort_by_key(|cell| (cell.y, cell.x));
+        covered.sort_by_key(|cell| (cell.y, cell.x));
+        assert_eq!(covered, expected);
+
+        rects
+            .iter()
+            .map(|rect| (rect.cell.x, rect.cell.y, rect.width, rect.height))
+            .collect()
+    }
+
+    #[test]
+    fn single_tile() {
+        assert_eq!(merge(&["   ", " # "]), [(1, 1, 1, 1)]);
+    }
+
+    #[test]
+    fn full_rectangle() {
+        assert_eq!(merge(&["####", "####", "####"]), [(0, 0, 4, 3)]);
+    }
+
+    #[test]
+    fn l_shape() {
+        assert_eq!(merge(&["#  ", "#  ", "###"]), [(0, 0, 1, 3), (1, 2, 2, 1)]);
+    }
+
+    #[test]
+    fn holes() {
+        assert_eq!(
+            merge(&["###", "# #", "###"]),
+            [(0, 0, 3, 1), (0, 1, 1, 2), (2, 1, 1, 2), (1, 2, 1, 1)]
+        );
+    }
+
+    #[test]
+    fn platforms_are_strips() {
+        assert_eq!(
+            merge(&["--##", "--##"]),
+            [(0, 0, 2, 1), (2, 0, 2, 2), (0, 1, 2, 1)]
+        );
+    }
+}
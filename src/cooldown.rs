@@ -0,0 +1,150 @@
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct CooldownPlugin;
+
+impl Plugin for CooldownPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, tick_cooldowns);
+    }
+}
+
+/// Limits how often something can be used
+///
+/// Every use takes a charge; charges come back one after another. Runs on virtual time,
+/// so it pauses with the game.
+#[derive(Component, Debug)]
+pub struct Cooldown {
+    pub charges: u32,
+    pub max_charges: u32,
+    /// Time until the next charge comes back
+    recharge: Timer,
+}
+
+impl Cooldown {
+    /// A single charge that comes back after `duration`
+    pub fn new(duration: Duration) -> Self {
+        Cooldown {
+            charges: 1,
+            max_charges: 1,
+            recharge: Timer::new(duration, TimerMode::Repeating),
+        }
+    }
+
+    /// Starts with all `max_charges`
+    pub fn with_charges(mut self, max_charges: u32) -> Self {
+        self.charges = max_charges;
+        self.max_charges = max_charges;
+        self
+    }
+
+    pub fn ready(&self) -> bool {
+        self.charges > 0
+    }
+
+    /// Takes a charge; returns false if there is none left
+    pub fn try_use(&mut self) -> bool {
+        if !self.ready() {
+            return false;
+        }
+        if self.charges == self.max_charges {
+            self.recharge.reset();
+        }
+        self.charges -= 1;
+        true
+    }
+}
+
+pub(crate) fn tick_cooldowns(time: Res<Time<Virtual>>, mut cooldowns: Query<&mut Cooldown>) {
+    for mut cooldown in &mut cooldowns {
+        if cooldown.charges == cooldown.max_charges {
+            continue;
+        }
+        cooldown.recharge.tick(time.delta());
+        let recharged = cooldown.recharge.times_finished_this_tick();
+        cooldown.charges = (cooldown.charges + recharged).min(cooldown.max_charges);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+
+    /// Ticks the cooldowns 100ms per update
+    fn app(cooldown: Cooldown) -> (App, Entity) {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .add_systems(Update, tick_cooldowns);
+        let entity = app.world_mut().spawn(cooldown).id();
+        (app, entity)
+    }
+
+    fn charges(app: &App, entity: Entity) -> u32 {
+        app.world().get::<Cooldown>(entity).unwrap().charges
+    }
+
+    fn use_charge(app: &mut App, entity: Entity) -> bool {
+        app.world_mut()
+            .get_mut::<Cooldown>(entity)
+            .unwrap()
+            .try_use()
+    }
+
+    #[test]
+    fn charges_run_out() {
+        let mut cooldown = Cooldown::new(Duration::from_secs(1)).with_charges(2);
+        assert!(cooldown.try_use());
+        assert!(cooldown.try_use());
+        assert!(!cooldown.ready());
+        assert!(!cooldown.try_use());
+        assert_eq!(cooldown.charges, 0);
+    }
+
+    #[test]
+    fn charges_come_back_one_after_another() {
+        let (mut app, entity) = app(Cooldown::new(Duration::from_millis(250)).with_charges(2));
+        app.update();
+        assert!(use_charge(&mut app, entity));
+        assert!(use_charge(&mut app, entity));
+        assert!(!use_charge(&mut app, entity));
+
+        for _ in 0..2 {
+            app.update();
+        }
+        assert_eq!(charges(&app, entity), 0);
+        app.update();
+        assert_eq!(charges(&app, entity), 1);
+        for _ in 0..2 {
+            app.update();
+        }
+        assert_eq!(charges(&app, entity), 2);
+        // full charges do not build up
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(charges(&app, entity), 2);
+    }
+
+    #[test]
+    fn pauses_with_the_game() {
+        let (mut app, entity) = app(Cooldown::new(Duration::from_millis(250)));
+        app.update();
+        assert!(use_charge(&mut app, entity));
+
+        app.world_mut().resource_mut::<Time<Virtual>>().pause();
+        for _ in 0..10 {
+            app.update();
+        }
+        assert_eq!(charges(&app, entity), 0);
+
+        app.world_mut().resource_mut::<Time<Virtual>>().unpause();
+        for _ in 0..3 {
+            app.update();
+        }
+        assert_eq!(charges(&app, entity), 1);
+    }
+}
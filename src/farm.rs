@@ -0,0 +1,591 @@
+use crate::cooldown::Cooldown;
+use crate::interaction::{Interactable, InteractionEvent};
+use crate::item::{spawn_item, Inventory, ItemKind};
+use crate::loading::{ImageAssets, LevelAssets, TILE_SIZE};
+use crate::map::{Farm, WaterTap};
+use crate::player::Player;
+use crate::power::Consumer;
+use crate::tiled::TERRAIN_TILE_COUNT;
+use crate::GameState;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use serde::Deserialize;
+use thiserror::Error;
+
+pub struct FarmPlugin;
+
+impl Plugin for FarmPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<CropConfig>()
+            .init_asset_loader::<CropConfigLoader>()
+            .add_systems(
+                Update,
+                (
+                    add_plots,
+                    grow_crops,
+                    tend_plots,
+                    take_water,
+                    update_plants,
+                    update_prompts,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_kept_plots);
+    }
+}
+
+/// How crops grow on the farm plots, as described in `assets/crops.ron`
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct CropConfig {
+    /// Stages a crop grows through after planting, at least two; crops in the last stage are ripe
+    pub stages: Vec<GrowthStage>,
+    /// Index into the terrain tile atlas for wilted plants
+    pub wilted_index: usize,
+    /// Crops harvested from a ripe plot
+    pub harvest: u32,
+    /// Water a plot holds at most; plots start full
+    pub max_water: f32,
+    /// Water added per unit of water
+    pub water_per_unit: f32,
+    /// Water used per second while growing
+    pub water_use: f32,
+    pub max_fertilizer: f32,
+    /// Fertilizer added per unit of waste
+    pub fertilizer_per_unit: f32,
+    /// Fertilizer used per second while growing
+    pub fertilizer_use: f32,
+    /// Growth speed multiplier while a plot has fertilizer
+    pub fertilizer_boost: f32,
+    /// Seconds plants survive without water
+    pub wilt_time: f32,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct GrowthStage {
+    /// Index into the terrain tile atlas
+    pub index: usize,
+    /// Seconds until the next stage without fertilizer; ripe crops wilt after this time
+    pub duration: f32,
+}
+
+/// Where crops grow; lives on [`Farm`] tiles
+#[derive(Component, Clone)]
+pub struct FarmPlot {
+    pub state: PlotState,
+    pub water: f32,
+    pub fertilizer: f32,
+    /// Seconds spent in the current stage, sped up by fertilizer
+    growth: f32,
+    /// Seconds without water
+    dry: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PlotState {
+    Empty,
+    /// Index into [`CropConfig::stages`]
+    Growing(usize),
+    Wilted,
+}
+
+impl FarmPlot {
+    fn is_ripe(&self, config: &CropConfig) -> bool {
+        self.state == PlotState::Growing(config.stages.len() - 1)
+    }
+
+    fn plant(&mut self) {
+        self.state = PlotState::Growing(0);
+        self.growth = 0.;
+        self.dry = 0.;
+    }
+}
+
+/// What happens when the player uses a plot
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PlotAction {
+    Harvest,
+    Clear,
+    /// Uses a crop as seed
+    Plant,
+    Water,
+    /// Uses waste as fertilizer
+    Fertilize,
+}
+
+impl PlotAction {
+    fn prompt(self) -> &'static str {
+        match self {
+            PlotAction::Harvest => "harvest the crops",
+            PlotAction::Clear => "clear the wilted plants",
+            PlotAction::Plant => "plant a crop",
+            PlotAction::Water => "water the plants",
+            PlotAction::Fertilize => "fertilize the plants",
+        }
+    }
+}
+
+fn next_action(plot: &FarmPlot, inventory: &Inventory, config: &CropConfig) -> Option<PlotAction> {
+    match plot.state {
+        PlotState::Wilted => Some(PlotAction::Clear),
+        _ if plot.is_ripe(config) => Some(PlotAction::Harvest),
+        PlotState::Empty if inventory.contains(ItemKind::Crop) => Some(PlotAction::Plant),
+        PlotState::Empty => None,
+        PlotState::Growing(_) => {
+            if plot.water < config.max_water && inventory.contains(ItemKind::Water) {
+                Some(PlotAction::Water)
+            } else if plot.fertilizer < config.max_fertilizer && inventory.contains(ItemKind::Waste)
+            {
+                Some(PlotAction::Fertilize)
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// Shown while there is nothing to do at a plot
+const IDLE_PROMPT: &str = "tend the farm";
+/// Plants stick out of the soil of the farm tile
+const PLANT_OFFSET: f32 = TILE_SIZE * 0.55;
+
+#[derive(Component)]
+struct Plant;
+
+/// Farm plots by position, kept while the ship is rebuilt after a reload
+///
+/// The farm tiles spawned at the same positions take them over.
+#[derive(Resource)]
+pub(crate) struct KeptPlots(HashMap<IVec2, FarmPlot>);
+
+/// Reads all farm plots before they are despawned
+#[derive(SystemParam)]
+pub(crate) struct PlotStates<'w, 's> {
+    plots: Query<'w, 's, (&'static FarmPlot, &'static Transform)>,
+}
+
+impl PlotStates<'_, '_> {
+    pub(crate) fn keep(&self) -> KeptPlots {
+        let plots = self
+            .plots
+            .iter()
+            .map(|(plot, transform)| (plot_position(transform), plot.clone()))
+            .collect();
+        KeptPlots(plots)
+    }
+}
+
+fn plot_position(transform: &Transform) -> IVec2 {
+    transform.translation.truncate().round().as_ivec2()
+}
+
+fn add_plots(
+    mut commands: Commands,
+    farms: Query<(Entity, &Transform), Added<Farm>>,
+    kept: Option<Res<KeptPlots>>,
+    assets: Res<ImageAssets>,
+    level_assets: Res<LevelAssets>,
+    configs: Res<Assets<CropConfig>>,
+) {
+    let config = configs.get(&level_assets.crops).unwrap();
+    for (entity, transform) in &farms {
+        let kept_plot = kept
+            .as_ref()
+            .and_then(|kept| kept.0.get(&plot_position(transform)));
+        let plot = kept_plot.cloned().unwrap_or(FarmPlot {
+            state: PlotState::Growing(0),
+            water: config.max_water,
+            fertilizer: 0.,
+            growth: 0.,
+            dry: 0.,
+        });
+        commands
+            .entity(entity)
+            .insert(plot)
+            .with_children(|parent| {
+                parent.spawn((
+                    SpriteBundle {
+                        texture: assets.tilemap.clone(),
+                        transform: Transform::from_xyz(0., PLANT_OFFSET, 0.1),
+                        ..default()
+                    },
+                    TextureAtlas {
+                        layout: assets.tilemap_layout.clone(),
+                        index: config.stages[0].index,
+                    },
+                    Plant,
+                ));
+            });
+    }
+    // the farm tiles of the reload arrive together with the kept plots
+    if kept.is_some() {
+        commands.remove_resource::<KeptPlots>();
+    }
+}
+
+fn clear_kept_plots(mut commands: Commands) {
+    commands.remove_resource::<KeptPlots>();
+}
+
+/// Crops use water and fertilizer while they grow and only grow while the farm lamps are on;
+/// they wilt without water or when they are not harvested in time
+fn grow_crops(
+    time: Res<Time>,
+    level_assets: Res<LevelAssets>,
+    configs: Res<Assets<CropConfig>>,
+    mut plots: Query<(&mut FarmPlot, Option<&Consumer>)>,
+) {
+    let config = configs.get(&level_assets.crops).unwrap();
+    let delta = time.delta_seconds();
+    for (mut plot, consumer) in &mut plots {
+        let unpowered = consumer.is_some_and(|consumer| !consumer.powered);
+        // supplies and timers change every frame, only a new state counts as a change
+        if let Some(state) = plot
+            .bypass_change_detection()
+            .grow(delta, unpowered, config)
+        {
+            plot.state = state;
+        }
+    }
+}
+
+impl FarmPlot {
+    /// Returns the new state if the crops grew into the next stage or wilted
+    fn grow(&mut self, delta: f32, unpowered: bool, config: &CropConfig) -> Option<PlotState> {
+        let PlotState::Growing(stage) = self.state else {
+            return None;
+        };
+        if self.water <= 0. {
+            self.dry += delta;
+            if self.dry >= config.wilt_time {
+                info!("The crops wilted without water");
+                return Some(PlotState::Wilted);
+            }
+            return None;
+        }
+        self.dry = 0.;
+
+        let last_stage = config.stages.len() - 1;
+        let stage = stage.min(last_stage);
+        if stage == last_stage {
+            self.growth += delta;
+            if self.growth >= config.stages[stage].duration {
+                info!("The crops wilted because they were not harvested");
+                return Some(PlotState::Wilted);
+            }
+            return None;
+        }
+        if unpowered {
+            return None;
+        }
+        self.water = (self.water - config.water_use * delta).max(0.);
+        let mut speed = 1.;
+        if self.fertilizer > 0. {
+            self.fertilizer = (self.fertilizer - config.fertilizer_use * delta).max(0.);
+            speed = config.fertilizer_boost;
+        }
+        self.growth += delta * speed;
+        if self.growth < config.stages[stage].duration {
+            return None;
+        }
+        self.growth = 0.;
+        Some(PlotState::Growing(stage + 1))
+    }
+}
+
+fn tend_plots(
+    mut commands: Commands,
+    mut events: EventReader<InteractionEvent>,
+    assets: Res<ImageAssets>,
+    level_assets: Res<LevelAssets>,
+    configs: Res<Assets<CropConfig>>,
+    mut plots: Query<(&mut FarmPlot, &GlobalTransform)>,
+    mut player: Query<&mut Inventory, With<Player>>,
+) {
+    let config = configs.get(&level_assets.crops).unwrap();
+    let Ok(mut inventory) = player.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok((mut plot, transform)) = plots.get_mut(event.entity) else {
+            continue;
+        };
+        // next_action only picks actions the player has the items for
+        match next_action(&plot, &inventory, config) {
+            Some(PlotAction::Harvest) => {
+                plot.state = PlotState::Empty;
+                let position = transform.translation().truncate() + Vec2::Y * TILE_SIZE;
+                for _ in 0..config.harvest {
+                    if !inventory.add(ItemKind::Crop) {
+                        spawn_item(&mut commands, &assets, ItemKind::Crop, position);
+                    }
+                }
+            }
+            Some(PlotAction::Clear) => plot.state = PlotState::Empty,
+            Some(PlotAction::Plant) => {
+                inventory.remove(ItemKind::Crop);
+                plot.plant();
+            }
+            Some(PlotAction::Water) => {
+                inventory.remove(ItemKind::Water);
+                plot.water = (plot.water + config.water_per_unit).min(config.max_water);
+            }
+            Some(PlotAction::Fertilize) => {
+                inventory.remove(ItemKind::Waste);
+                plot.fertilizer =
+                    (plot.fertilizer + config.fertilizer_per_unit).min(config.max_fertilizer);
+            }
+            None => (),
+        }
+    }
+}
+
+fn take_water(
+    mut events: EventReader<InteractionEvent>,
+    mut taps: Query<Option<&mut Cooldown>, With<WaterTap>>,
+    mut player: Query<&mut Inventory, With<Player>>,
+) {
+    let Ok(mut inventory) = player.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok(cooldown) = taps.get_mut(event.entity) else {
+            continue;
+        };
+        if !inventory.add(ItemKind::Water) {
+            info!("No room for the water");
+        } else if let Some(mut cooldown) = cooldown {
+            cooldown.try_use();
+        }
+    }
+}
+
+/// Shows the stage of the crops
+fn update_plants(
+    level_assets: Res<LevelAssets>,
+    configs: Res<Assets<CropConfig>>,
+    plots: Query<(&FarmPlot, &Children), Changed<FarmPlot>>,
+    mut plants: Query<(&mut TextureAtlas, &mut Visibility), With<Plant>>,
+) {
+    let config = configs.get(&level_assets.crops).unwrap();
+    for (plot, children) in &plots {
+        let index = match plot.state {
+            PlotState::Empty => None,
+            PlotState::Growing(stage) => {
+                Some(config.stages[stage.min(config.stages.len() - 1)].index)
+            }
+            PlotState::Wilted => Some(config.wilted_index),
+        };
+        let mut plants = plants.iter_many_mut(children);
+        while let Some((mut atlas, mut visibility)) = plants.fetch_next() {
+            match index {
+                Some(index) => {
+                    atlas.index = index;
+                    *visibility = Visibility::Inherited;
+                }
+                None => *visibility = Visibility::Hidden,
+            }
+        }
+    }
+}
+
+/// The prompt of a plot tells what using it does with the items the player carries
+fn update_prompts(
+    level_assets: Res<LevelAssets>,
+    configs: Res<Assets<CropConfig>>,
+    mut plots: Query<(&FarmPlot, &mut Interactable)>,
+    player: Query<&Inventory, With<Player>>,
+) {
+    let config = configs.get(&level_assets.crops).unwrap();
+    let Ok(inventory) = player.get_single() else {
+        return;
+    };
+    for (plot, mut interactable) in &mut plots {
+        let prompt = next_action(plot, inventory, config).map_or(IDLE_PROMPT, PlotAction::prompt);
+        if interactable.prompt != prompt {
+            interactable.prompt = prompt.to_owned();
+        }
+    }
+}
+
+#[derive(Default)]
+struct CropConfigLoader;
+
+#[derive(Debug, Error)]
+pub enum CropConfigError {
+    #[error("Could not read crop config: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse crop config: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Crops need at least two growth stages, so they are not ripe when planted")]
+    TooFewStages,
+    #[error("Tile index {0} is outside of the terrain tile atlas ({TERRAIN_TILE_COUNT} tiles)")]
+    InvalidTileIndex(usize),
+    #[error("'{0}' has to be positive")]
+    NotPositive(&'static str),
+}
+
+impl AssetLoader for CropConfigLoader {
+    type Asset = CropConfig;
+    type Settings = ();
+    type Error = CropConfigError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let config: CropConfig = ron::de::from_bytes(&bytes)?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["crops.ron"]
+    }
+}
+
+impl CropConfig {
+    fn validate(&self) -> Result<(), CropConfigError> {
+        if self.stages.len() < 2 {
+            return Err(CropConfigError::TooFewStages);
+        }
+        let indices = self.stages.iter().map(|stage| stage.index);
+        if let Some(index) = indices
+            .chain([self.wilted_index])
+            .find(|&index| index >= TERRAIN_TILE_COUNT)
+        {
+            return Err(CropConfigError::InvalidTileIndex(index));
+        }
+        let values = [
+            (
+                "duration",
+                self.stages
+                    .iter()
+                    .map(|stage| stage.duration)
+                    .fold(f32::MAX, f32::min),
+            ),
+            ("max_water", self.max_water),
+            ("water_per_unit", self.water_per_unit),
+            ("max_fertilizer", self.max_fertilizer),
+            ("fertilizer_per_unit", self.fertilizer_per_unit),
+            ("fertilizer_boost", self.fertilizer_boost),
+            ("wilt_time", self.wilt_time),
+        ];
+        if let Some((name, _)) = values.into_iter().find(|(_, value)| *value <= 0.) {
+            return Err(CropConfigError::NotPositive(name));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> CropConfig {
+        let config: CropConfig =
+            ron::de::from_bytes(&std::fs::read("assets/crops.ron").unwrap()).unwrap();
+        config.validate().unwrap();
+        config
+    }
+
+    fn planted(config: &CropConfig) -> FarmPlot {
+        FarmPlot {
+            state: PlotState::Growing(0),
+            water: config.max_water,
+            fertilizer: 0.,
+            growth: 0.,
+            dry: 0.,
+        }
+    }
+
+    /// Grows the plot in steps of 0.1 seconds until its state changes
+    fn seconds_until_next_state(plot: &mut FarmPlot, config: &CropConfig) -> (f32, PlotState) {
+        for step in 1..10_000 {
+            if let Some(state) = plot.grow(0.1, false, config) {
+                plot.state = state;
+                return (step as f32 * 0.1, state);
+            }
+        }
+        panic!("the plot stays {:?}", plot.state);
+    }
+
+    #[test]
+    fn crops_grow_through_the_stages() {
+        let config = config();
+        let mut plot = planted(&config);
+        for stage in 1..config.stages.len() {
+            let (seconds, state) = seconds_until_next_state(&mut plot, &config);
+            assert_eq!(state, PlotState::Growing(stage));
+            assert!((seconds - config.stages[stage - 1].duration).abs() < 0.15);
+        }
+        assert!(plot.is_ripe(&config));
+        assert!(plot.water < config.max_water);
+    }
+
+    #[test]
+    fn fertilizer_speeds_up_growth() {
+        let config = config();
+        let mut plot = planted(&config);
+        plot.fertilizer = config.max_fertilizer;
+        let (seconds, state) = seconds_until_next_state(&mut plot, &config);
+        assert_eq!(state, PlotState::Growing(1));
+        let expected = config.stages[0].duration / config.fertilizer_boost;
+        assert!((seconds - expected).abs() < 0.15);
+        assert!(plot.fertilizer < config.max_fertilizer);
+    }
+
+    #[test]
+    fn crops_do_not_grow_without_power() {
+        let config = config();
+        let mut plot = planted(&config);
+        assert_eq!(
+            plot.grow(config.stages[0].duration * 2., true, &config),
+            None
+        );
+        assert_eq!(plot.state, PlotState::Growing(0));
+        assert_eq!(plot.water, config.max_water);
+    }
+
+    #[test]
+    fn crops_wilt_without_water() {
+        let config = config();
+        let mut plot = planted(&config);
+        plot.water = 0.;
+        let (seconds, state) = seconds_until_next_state(&mut plot, &config);
+        assert_eq!(state, PlotState::Wilted);
+        assert!((seconds - config.wilt_time).abs() < 0.15);
+    }
+
+    #[test]
+    fn ripe_crops_wilt_when_not_harvested() {
+        let config = config();
+        let last_stage = config.stages.len() - 1;
+        let mut plot = FarmPlot {
+            state: PlotState::Growing(last_stage),
+            ..planted(&config)
+        };
+        let (seconds, state) = seconds_until_next_state(&mut plot, &config);
+        assert_eq!(state, PlotState::Wilted);
+        assert!((seconds - config.stages[last_stage].duration).abs() < 0.15);
+    }
+
+    #[test]
+    fn rejects_a_single_stage() {
+        let mut config = config();
+        config.stages.truncate(1);
+        assert!(matches!(
+            config.validate(),
+            Err(CropConfigError::TooFewStages)
+        ));
+    }
+}
@@ -0,0 +1,185 @@
+use crate::palette::{PaletteColor, TileKind};
+use bevy::prelude::*;
+use bevy::render::render_resource::TextureFormat;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+/// Tile kinds of a map, row by row starting at the top left
+pub struct MapGrid {
+    pub width: usize,
+    pub cells: Vec<Option<TileKind>>,
+}
+
+#[derive(Debug, Error)]
+pub enum MapError {
+    #[error(
+        "Map image has the unsupported texture format {0:?}; export it as RGBA, BGRA or grayscale"
+    )]
+    UnsupportedFormat(TextureFormat),
+    #[error(
+        "Map image of {width}x{height} pixels should contain {expected} bytes, but has {actual}"
+    )]
+    InvalidData {
+        width: usize,
+        height: usize,
+        expected: usize,
+        actual: usize,
+    },
+}
+
+impl MapGrid {
+    pub fn from_image(image: &Image) -> Result<MapGrid, MapError> {
+        let size = image.texture_descriptor.size;
+        let (width, height) = (size.width as usize, size.height as usize);
+        let format = image.texture_descriptor.format;
+        let bytes_per_pixel = match format {
+            TextureFormat::Rgba8Unorm
+            | TextureFormat::Rgba8UnormSrgb
+            | TextureFormat::Bgra8Unorm
+            | TextureFormat::Bgra8UnormSrgb => 4,
+            TextureFormat::Rgba16Unorm => 8,
+            TextureFormat::Rg8Unorm => 2,
+            TextureFormat::R8Unorm => 1,
+            _ => return Err(MapError::UnsupportedFormat(format)),
+        };
+        let expected = width * height * bytes_per_pixel;
+        if image.data.len() < expected {
+            return Err(MapError::InvalidData {
+                width,
+                height,
+                expected,
+                actual: image.data.len(),
+            });
+        }
+
+        // first position and number of pixels of each unknown color
+        let mut unknown = BTreeMap::<[u8; 3], ((usize, usize), usize)>::new();
+        let cells = image.data[..expected]
+            .chunks_exact(bytes_per_pixel)
+            .enumerate()
+            .map(|(tile, pixel)| {
+                let [red, green, blue, alpha] = to_rgba(format, pixel);
+                match PaletteColor::from_rgba([red, green, blue, alpha]) {
+                    PaletteColor::Empty => None,
+                    PaletteColor::Tile(kind) => Some(kind),
+                    PaletteColor::Unknown => {
+                        unknown
+                            .entry([red, green, blue])
+                            .or_insert(((tile % width, tile / width), 0))
+                            .1 += 1;
+                        None
+                    }
+                }
+            })
+            .collect();
+        for (color, (position, count)) in unknown {
+            warn!(
+                "Unknown color {color:?} in map at {position:?} and {} more pixels, add it to the palette",
+                count - 1
+            );
+        }
+
+        Ok(MapGrid { width, cells })
+    }
+
+    pub fn height(&self) -> usize {
+        self.cells.len() / self.width
+    }
+
+    /// Whether the cell has the given kind; cells outside of the map count as connected,
+    /// so terrain continues past the edges
+    pub fn connects(&self, x: isize, y: isize, kind: TileKind) -> bool {
+        if x < 0 || y < 0 || x >= self.width as isize || y >= self.height() as isize {
+            return true;
+        }
+        self.cells[y as usize * self.width + x as usize] == Some(kind)
+    }
+
+    pub fn tiles(&self) -> impl Iterator<Item = (usize, usize, TileKind)> + '_ {
+        self.cells.iter().enumerate().filter_map(|(tile, kind)| {
+            kind.map(|kind| (tile % self.width, tile / self.width, kind))
+        })
+    }
+}
+
+fn to_rgba(format: TextureFormat, pixel: &[u8]) -> [u8; 4] {
+    match format {
+        TextureFormat::Bgra8Unorm | TextureFormat::Bgra8UnormSrgb => {
+            [pixel[2], pixel[1], pixel[0], pixel[3]]
+        }
+        // little endian 16 bit channels; the high byte is close enough for palette colors
+        TextureFormat::Rgba16Unorm => [pixel[1], pixel[3], pixel[5], pixel[7]],
+        // grayscale with alpha
+        TextureFormat::Rg8Unorm => [pixel[0], pixel[0], pixel[0], pixel[1]],
+        TextureFormat::R8Unorm => [pixel[0], pixel[0], pixel[0], 255],
+        _ => [pixel[0], pixel[1], pixel[2], pixel[3]],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::render::render_asset::RenderAssetUsages;
+    use bevy::render::render_resource::{Extent3d, TextureDimension};
+
+    fn image(width: u32, height: u32, format: TextureFormat, data: Vec<u8>) -> Image {
+        let size = Extent3d {
+            width,
+            height,
+            depth_or_array_layers: 1,
+        };
+        Image::new(
+            size,
+            TextureDimension::D2,
+            data,
+            format,
+            RenderAssetUsages::default(),
+        )
+    }
+
+    #[test]
+    fn reads_palette_colors() {
+        #[rustfmt::skip]
+        let data = vec![
+            1, 90, 10, 255,   0, 0, 0, 0,
+            0, 0, 0, 255,     7, 7, 7, 255,
+            7, 7, 7, 255,     140, 90, 40, 255,
+        ];
+        let grid = MapGrid::from_image(&image(2, 3, TextureFormat::Rgba8UnormSrgb, data)).unwrap();
+
+        assert_eq!((grid.width, grid.height()), (2, 3));
+        assert_eq!(
+            grid.cells,
+            [
+                Some(TileKind::Ground),
+                None,
+                // black is empty, unknown colors are skipped
+                None,
+                None,
+                None,
+                Some(TileKind::Platform)
+            ]
+        );
+    }
+
+    #[test]
+    fn black_is_the_background_of_grayscale_maps() {
+        let grid =
+            MapGrid::from_image(&image(3, 1, TextureFormat::R8Unorm, vec![0, 200, 0])).unwrap();
+        assert_eq!(grid.cells, [None, Some(TileKind::Spikes), None]);
+    }
+
+    #[test]
+    fn rejects_short_data() {
+        let mut short = image(2, 2, TextureFormat::R8Unorm, vec![0; 4]);
+        short.data.pop();
+        assert!(matches!(
+            MapGrid::from_image(&short),
+            Err(MapError::InvalidData {
+                expected: 4,
+                actual: 3,
+                ..
+            })
+        ));
+    }
+}
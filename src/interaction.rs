@@ -0,0 +1,172 @@
+use crate::cooldown::{tick_cooldowns, Cooldown};
+use crate::launch::CONSOLE_PROMPT;
+use crate::loading::TILE_SIZE;
+use crate::map::{Airlock, Console, Farm, TankInput, Toilet, WaterTap};
+use crate::pipes::Pump;
+use crate::player::Player;
+use crate::GameState;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct InteractionPlugin;
+
+impl Plugin for InteractionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Focus>()
+            .add_event::<InteractionEvent>()
+            .add_systems(
+                Update,
+                (add_interactables, find_focus, interact)
+                    .chain()
+                    .after(tick_cooldowns)
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_focus);
+    }
+}
+
+/// Something the player can interact with when standing close to it
+///
+/// Entities with a [`Cooldown`] can only be used while they have charges left; the system
+/// handling the [`InteractionEvent`] takes a charge once something happens.
+#[derive(Component)]
+pub struct Interactable {
+    /// What happens, shown as "Press F to {prompt}"
+    pub prompt: String,
+    pub key: KeyCode,
+    /// Maximum distance between the centers of the player and the entity
+    pub range: f32,
+}
+
+impl Interactable {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Interactable {
+            prompt: prompt.into(),
+            key: KeyCode::KeyF,
+            range: TILE_SIZE,
+        }
+    }
+
+    pub fn with_key(mut self, key: KeyCode) -> Self {
+        self.key = key;
+        self
+    }
+
+    pub fn with_range(mut self, range: f32) -> Self {
+        self.range = range;
+        self
+    }
+
+    /// Text shown while the player is close enough to interact
+    pub fn prompt_text(&self) -> String {
+        let key = format!("{:?}", self.key);
+        let key = key.strip_prefix("Key").unwrap_or(&key);
+        format!("Press {key} to {}", self.prompt)
+    }
+}
+
+/// Sent when the player interacts with an [`Interactable`]
+#[derive(Event)]
+pub struct InteractionEvent {
+    pub entity: Entity,
+}
+
+/// The closest [`Interactable`] in range of the player
+#[derive(Resource, Default)]
+pub struct Focus(pub Option<Entity>);
+
+#[allow(clippy::too_many_arguments)]
+fn add_interactables(
+    mut commands: Commands,
+    toilets: Query<Entity, Added<Toilet>>,
+    farms: Query<Entity, Added<Farm>>,
+    tank_inputs: Query<Entity, Added<TankInput>>,
+    airlocks: Query<Entity, Added<Airlock>>,
+    water_taps: Query<Entity, Added<WaterTap>>,
+    pumps: Query<(Entity, &Pump), Added<Pump>>,
+    consoles: Query<Entity, Added<Console>>,
+) {
+    for entity in &toilets {
+        commands
+            .entity(entity)
+            .insert(Interactable::new("use the toilet").with_range(TILE_SIZE * 1.5));
+    }
+    for entity in &farms {
+        commands
+            .entity(entity)
+            .insert(Interactable::new("tend the farm"));
+    }
+    for entity in &tank_inputs {
+        commands.entity(entity).insert((
+            Interactable::new("fill the tank"),
+            Cooldown::new(Duration::from_secs(1)).with_charges(3),
+        ));
+    }
+    for entity in &airlocks {
+        commands
+            .entity(entity)
+            .insert(Interactable::new("refill oxygen"));
+    }
+    for entity in &water_taps {
+        commands.entity(entity).insert((
+            Interactable::new("take water"),
+            Cooldown::new(Duration::from_secs(2)),
+        ));
+    }
+    for (entity, pump) in &pumps {
+        commands
+            .entity(entity)
+            .insert(Interactable::new(pump.prompt()));
+    }
+    for entity in &consoles {
+        commands
+            .entity(entity)
+            .insert(Interactable::new(CONSOLE_PROMPT).with_key(KeyCode::KeyE));
+    }
+}
+
+fn find_focus(
+    player: Query<&GlobalTransform, With<Player>>,
+    interactables: Query<(Entity, &GlobalTransform, &Interactable)>,
+    mut focus: ResMut<Focus>,
+) {
+    let closest = player.get_single().ok().and_then(|player| {
+        let position = player.translation().truncate();
+        interactables
+            .iter()
+            .map(|(entity, transform, interactable)| {
+                let distance = position.distance(transform.translation().truncate());
+                (entity, distance, interactable.range)
+            })
+            .filter(|&(_, distance, range)| distance <= range)
+            .min_by(|(_, a, _), (_, b, _)| a.total_cmp(b))
+            .map(|(entity, _, _)| entity)
+    });
+    if focus.0 != closest {
+        focus.0 = closest;
+    }
+}
+
+fn interact(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    focus: Res<Focus>,
+    interactables: Query<(&Interactable, Option<&Cooldown>)>,
+    mut events: EventWriter<InteractionEvent>,
+) {
+    let Some(entity) = focus.0 else {
+        return;
+    };
+    let Ok((interactable, cooldown)) = interactables.get(entity) else {
+        return;
+    };
+    if !keyboard.just_pressed(interactable.key)
+        || cooldown.is_some_and(|cooldown| !cooldown.ready())
+    {
+        return;
+    }
+    events.send(InteractionEvent { entity });
+}
+
+fn clear_focus(mut focus: ResMut<Focus>) {
+    focus.0 = None;
+}
@@ -0,0 +1,207 @@
+use crate::interaction::{Interactable, InteractionEvent};
+use crate::loading::{ImageAssets, TILE_SIZE};
+use crate::physics::GameLayer;
+use crate::player::{Facing, Player};
+use crate::GameState;
+use avian2d::prelude::*;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+pub struct ItemPlugin;
+
+impl Plugin for ItemPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (pick_up, select_slot, drop_item).run_if(in_state(GameState::Playing)),
+        )
+        .add_systems(OnExit(GameState::Playing), despawn_items);
+    }
+}
+
+/// Something the player can carry around; stations make and use them
+#[derive(Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ItemKind {
+    /// Bag of waste from the toilet
+    Waste,
+    Crop,
+    /// Canister of fuel
+    Fuel,
+    /// Used for repairs
+    Tool,
+    /// Bucket of water for the farm
+    Water,
+}
+
+impl ItemKind {
+    pub fn name(self) -> &'static str {
+        match self {
+            ItemKind::Waste => "waste",
+            ItemKind::Crop => "crop",
+            ItemKind::Fuel => "fuel",
+            ItemKind::Tool => "tool",
+            ItemKind::Water => "water",
+        }
+    }
+
+    /// Index into the terrain tile atlas
+    fn atlas_index(self) -> usize {
+        match self {
+            ItemKind::Waste => 6,
+            ItemKind::Crop => 128,
+            ItemKind::Fuel => 67,
+            ItemKind::Tool => 27,
+            ItemKind::Water => 33,
+        }
+    }
+}
+
+/// Item lying around in the world
+#[derive(Component)]
+pub struct Item(pub ItemKind);
+
+pub const INVENTORY_SLOTS: usize = 3;
+
+/// Items carried by the player; dropping and throwing uses the selected slot
+#[derive(Component, Default)]
+pub struct Inventory {
+    pub slots: [Option<ItemKind>; INVENTORY_SLOTS],
+    pub selected: usize,
+}
+
+impl Inventory {
+    /// Puts the item into the selected slot if it is free, otherwise into the first free slot;
+    /// returns false if all slots are taken
+    pub fn add(&mut self, kind: ItemKind) -> bool {
+        let slot = if self.slots[self.selected].is_none() {
+            Some(self.selected)
+        } else {
+            self.slots.iter().position(Option::is_none)
+        };
+        let Some(slot) = slot else {
+            return false;
+        };
+        self.slots[slot] = Some(kind);
+        true
+    }
+
+    /// Removes an item of the given kind, preferably from the selected slot
+    pub fn remove(&mut self, kind: ItemKind) -> bool {
+        let slot = if self.slots[self.selected] == Some(kind) {
+            Some(self.selected)
+        } else {
+            self.slots.iter().position(|slot| *slot == Some(kind))
+        };
+        let Some(slot) = slot else {
+            return false;
+        };
+        self.slots[slot] = None;
+        true
+    }
+
+    pub fn contains(&self, kind: ItemKind) -> bool {
+        self.slots.contains(&Some(kind))
+    }
+
+    pub fn take_selected(&mut self) -> Option<ItemKind> {
+        self.slots[self.selected].take()
+    }
+}
+
+/// Items are drawn smaller than tiles
+const ITEM_SCALE: f32 = 0.75;
+/// Velocity of thrown items when the player faces right
+const THROW_VELOCITY: Vec2 = Vec2::new(150., 80.);
+
+pub(crate) fn spawn_item<'a>(
+    commands: &'a mut Commands,
+    assets: &ImageAssets,
+    kind: ItemKind,
+    position: Vec2,
+) -> EntityCommands<'a> {
+    commands.spawn((
+        SpriteBundle {
+            texture: assets.tilemap.clone(),
+            // in front of the ship
+            transform: Transform::from_translation(position.extend(0.5))
+                .with_scale(Vec3::splat(ITEM_SCALE)),
+            ..default()
+        },
+        TextureAtlas {
+            layout: assets.tilemap_layout.clone(),
+            index: kind.atlas_index(),
+        },
+        Item(kind),
+        Interactable::new(format!("pick up {}", kind.name())),
+        RigidBody::Dynamic,
+        // scaled with the sprite
+        Collider::rectangle(TILE_SIZE, TILE_SIZE),
+        LockedAxes::ROTATION_LOCKED,
+        CollisionLayers::new(GameLayer::Item, GameLayer::Ground),
+    ))
+}
+
+fn pick_up(
+    mut commands: Commands,
+    mut events: EventReader<InteractionEvent>,
+    items: Query<&Item>,
+    mut player: Query<&mut Inventory, With<Player>>,
+) {
+    let Ok(mut inventory) = player.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok(item) = items.get(event.entity) else {
+            continue;
+        };
+        if inventory.add(item.0) {
+            commands.entity(event.entity).despawn();
+        } else {
+            info!("No room for the {}", item.0.name());
+        }
+    }
+}
+
+fn select_slot(keyboard: Res<ButtonInput<KeyCode>>, mut player: Query<&mut Inventory>) {
+    const KEYS: [KeyCode; INVENTORY_SLOTS] = [KeyCode::Digit1, KeyCode::Digit2, KeyCode::Digit3];
+    for mut inventory in &mut player {
+        if let Some(slot) = KEYS.iter().position(|&key| keyboard.just_pressed(key)) {
+            inventory.selected = slot;
+        }
+    }
+}
+
+/// Drops the selected item on G and throws it on T
+fn drop_item(
+    mut commands: Commands,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    assets: Res<ImageAssets>,
+    mut player: Query<(&Transform, &Facing, &mut Inventory), With<Player>>,
+) {
+    let throw = keyboard.just_pressed(KeyCode::KeyT);
+    if !throw && !keyboard.just_pressed(KeyCode::KeyG) {
+        return;
+    }
+    let Ok((transform, facing, mut inventory)) = player.get_single_mut() else {
+        return;
+    };
+    let Some(kind) = inventory.take_selected() else {
+        return;
+    };
+    let mut item = spawn_item(
+        &mut commands,
+        &assets,
+        kind,
+        transform.translation.truncate(),
+    );
+    if throw {
+        item.insert(LinearVelocity(THROW_VELOCITY * Vec2::new(facing.0, 1.)));
+    }
+}
+
+fn despawn_items(mut commands: Commands, items: Query<Entity, With<Item>>) {
+    for entity in &items {
+        commands.entity(entity).despawn();
+    }
+}
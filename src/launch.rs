@@ -0,0 +1,298 @@
+use crate::camera::FollowCamera;
+use crate::colliders::StaticCell;
+use crate::interaction::{Interactable, InteractionEvent};
+use crate::item::Item;
+use crate::loading::TILE_SIZE;
+use crate::map::{Console, Engine, Level, MapCommand, ShipTile};
+use crate::pipes::{PipeNode, PipeNodes, Pipes};
+use crate::player::Player;
+use crate::rng::{GameRng, RngStream};
+use crate::tank::FuelLevel;
+use crate::terrain::PlanetSeed;
+use crate::GameState;
+use avian2d::prelude::*;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use bevy_tnua::controller::TnuaControllerBundle;
+use rand::Rng;
+
+pub struct LaunchPlugin;
+
+impl Plugin for LaunchPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Cycle>()
+            .add_systems(
+                Update,
+                (
+                    (update_console_prompts, start_launch).run_if(not(resource_exists::<Launch>)),
+                    launch.run_if(resource_exists::<Launch>),
+                    update_particles,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), end_launch)
+            .add_systems(OnEnter(GameState::Restart), next_cycle)
+            // before the level is built when entering `GameState::Playing`
+            .add_systems(OnExit(GameState::Menu), reseed_rng)
+            .add_systems(OnExit(GameState::Restart), reseed_rng);
+    }
+}
+
+/// Number of times the ship has launched
+#[derive(Resource, Default)]
+pub struct Cycle(pub u32);
+
+/// The ship is launching; inserted when the launch starts
+#[derive(Resource)]
+struct Launch {
+    timer: Timer,
+    /// Tanks that feed the engines; they are emptied during the launch
+    tanks: Vec<Entity>,
+    /// Where the thrust comes out: below each engine at the start
+    exhausts: Vec<Vec2>,
+    /// Distance the ship has moved up
+    height: f32,
+    particle_timer: Timer,
+}
+
+/// Item that lifts off with the ship because it was inside of it at the start of the launch
+#[derive(Component)]
+struct Aboard;
+
+#[derive(Component)]
+struct ThrustParticle {
+    velocity: Vec2,
+    lifetime: Timer,
+}
+
+/// Seconds the engines burn before the ship lifts off
+const IGNITION: f32 = 1.;
+const LAUNCH_DURATION: f32 = 4.;
+/// Upwards acceleration of the ship after ignition in pixels per second squared
+const LIFTOFF_ACCELERATION: f32 = 80.;
+const PARTICLE_INTERVAL: f32 = 0.02;
+const PARTICLE_LIFETIME: f32 = 0.6;
+
+pub(crate) const CONSOLE_PROMPT: &str = "launch the ship";
+
+/// Engines and the tanks piped to them
+#[derive(SystemParam)]
+struct EngineTanks<'w, 's> {
+    engines: Query<'w, 's, (&'static PipeNode, &'static Transform), With<Engine>>,
+    pipes: Res<'w, Pipes>,
+    nodes: PipeNodes<'w, 's>,
+    tanks: Query<'w, 's, &'static FuelLevel>,
+}
+
+impl EngineTanks<'_, '_> {
+    /// The tanks that feed the engines if they are ready for a launch, otherwise why not
+    fn ready(&self) -> Result<Vec<Entity>, &'static str> {
+        let mut feeding_tanks: Vec<Entity> = self
+            .engines
+            .iter()
+            .flat_map(|(node, _)| self.pipes.upstream(&node.0))
+            .filter_map(|name| self.nodes.get(name))
+            .filter(|&entity| self.tanks.contains(entity))
+            .collect();
+        feeding_tanks.sort();
+        feeding_tanks.dedup();
+        if feeding_tanks.is_empty() {
+            return Err("no tank is piped to the engines");
+        }
+        if !feeding_tanks
+            .iter()
+            .all(|&entity| self.tanks.get(entity).is_ok_and(FuelLevel::is_full))
+        {
+            return Err("fill the tanks that feed the engines first");
+        }
+        Ok(feeding_tanks)
+    }
+}
+
+/// The console tells why the ship cannot launch yet
+fn update_console_prompts(
+    engine_tanks: EngineTanks,
+    mut consoles: Query<&mut Interactable, With<Console>>,
+) {
+    let prompt = match engine_tanks.ready() {
+        Ok(_) => CONSOLE_PROMPT.to_owned(),
+        Err(reason) => format!("{CONSOLE_PROMPT} ({reason})"),
+    };
+    for mut interactable in &mut consoles {
+        if interactable.prompt != prompt {
+            interactable.prompt.clone_from(&prompt);
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn start_launch(
+    mut commands: Commands,
+    mut events: EventReader<InteractionEvent>,
+    consoles: Query<(), With<Console>>,
+    engine_tanks: EngineTanks,
+    player: Query<Entity, With<Player>>,
+    ship_cells: Query<Entity, (With<ShipTile>, With<StaticCell>)>,
+    ship_sprites: Query<&Transform, (With<ShipTile>, With<Sprite>)>,
+    items: Query<(Entity, &Transform), With<Item>>,
+) {
+    let Ok(player) = player.get_single() else {
+        return;
+    };
+    let used_console = events.read().any(|event| consoles.contains(event.entity));
+    if !used_console {
+        return;
+    }
+
+    let feeding_tanks = match engine_tanks.ready() {
+        Ok(feeding_tanks) => feeding_tanks,
+        Err(reason) => {
+            info!("The ship cannot launch: {reason}");
+            return;
+        }
+    };
+
+    let exhausts = engine_tanks
+        .engines
+        .iter()
+        .map(|(_, transform)| transform.translation.truncate() - Vec2::Y * TILE_SIZE / 2.)
+        .collect();
+    commands.insert_resource(Launch {
+        timer: Timer::from_seconds(LAUNCH_DURATION, TimerMode::Once),
+        tanks: feeding_tanks,
+        exhausts,
+        height: 0.,
+        particle_timer: Timer::from_seconds(PARTICLE_INTERVAL, TimerMode::Repeating),
+    });
+    // the player rides along instead of being controlled
+    commands
+        .entity(player)
+        .remove::<TnuaControllerBundle>()
+        .insert((RigidBody::Kinematic, LinearVelocity::ZERO));
+    // merged colliders stay on the ground, so the ship gets colliders that lift off with it
+    for entity in &ship_cells {
+        commands
+            .entity(entity)
+            .remove::<StaticCell>()
+            .add_collider();
+    }
+    // items inside of the ship lift off with it, the ones outside stay on the planet
+    let ship = ship_sprites
+        .iter()
+        .map(|transform| {
+            Rect::from_center_size(transform.translation.truncate(), Vec2::splat(TILE_SIZE))
+        })
+        .reduce(|bounds, tile| bounds.union(tile));
+    for (entity, transform) in &items {
+        if ship.is_some_and(|ship| ship.contains(transform.translation.truncate())) {
+            commands
+                .entity(entity)
+                .insert((Aboard, RigidBody::Kinematic, LinearVelocity::ZERO));
+        }
+    }
+}
+
+#[allow(clippy::too_many_arguments, clippy::type_complexity)]
+fn launch(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut launch: ResMut<Launch>,
+    mut tanks: Query<&mut FuelLevel>,
+    mut rng: ResMut<GameRng>,
+    mut ship: Query<&mut Transform, Or<(With<ShipTile>, With<Player>, With<Aboard>)>>,
+    mut camera: Query<&mut FollowCamera>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    launch.timer.tick(time.delta());
+    let elapsed = launch.timer.elapsed_secs();
+    let mut feeding_tanks = tanks.iter_many_mut(&launch.tanks);
+    while let Some(mut tank) = feeding_tanks.fetch_next() {
+        tank.amount = tank.capacity * launch.timer.fraction_remaining();
+    }
+
+    let liftoff = (elapsed - IGNITION).max(0.);
+    let height = LIFTOFF_ACCELERATION * liftoff * liftoff / 2.;
+    let rise = height - launch.height;
+    launch.height = height;
+    for mut transform in &mut ship {
+        transform.translation.y += rise;
+    }
+
+    for mut camera in &mut camera {
+        camera.trauma = camera.trauma.max(0.6);
+    }
+
+    launch.particle_timer.tick(time.delta());
+    let rng = rng.stream(RngStream::Effects);
+    for _ in 0..launch.particle_timer.times_finished_this_tick() {
+        let exhaust = launch.exhausts[rng.gen_range(0..launch.exhausts.len())];
+        let spread = Vec2::X * rng.gen_range(-TILE_SIZE / 3. ..TILE_SIZE / 3.);
+        let position = exhaust + spread + Vec2::Y * launch.height;
+        let velocity = Vec2::new(rng.gen_range(-20. ..20.), rng.gen_range(-120. ..-60.));
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: Color::srgb(1., rng.gen_range(0.4..0.9), 0.1),
+                    custom_size: Some(Vec2::splat(rng.gen_range(2. ..5.))),
+                    ..default()
+                },
+                transform: Transform::from_translation(position.extend(1.)),
+                ..default()
+            },
+            ThrustParticle {
+                velocity,
+                lifetime: Timer::from_seconds(PARTICLE_LIFETIME, TimerMode::Once),
+            },
+        ));
+    }
+
+    if launch.timer.finished() {
+        state.set(GameState::Restart);
+    }
+}
+
+fn update_particles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut particles: Query<(Entity, &mut ThrustParticle, &mut Transform, &mut Sprite)>,
+) {
+    for (entity, mut particle, mut transform, mut sprite) in &mut particles {
+        particle.lifetime.tick(time.delta());
+        if particle.lifetime.finished() {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        transform.translation += (particle.velocity * time.delta_seconds()).extend(0.);
+        sprite
+            .color
+            .set_alpha(particle.lifetime.fraction_remaining());
+    }
+}
+
+fn end_launch(mut commands: Commands, particles: Query<Entity, With<ThrustParticle>>) {
+    commands.remove_resource::<Launch>();
+    for entity in &particles {
+        commands.entity(entity).despawn();
+    }
+}
+
+/// Lands on the next planet: random planets get a new seed, the other levels start over
+fn next_cycle(
+    mut cycle: ResMut<Cycle>,
+    level: Res<Level>,
+    mut planet_seed: ResMut<PlanetSeed>,
+    mut rng: ResMut<GameRng>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    cycle.0 += 1;
+    rng.reseed(cycle.0);
+    if *level == Level::Random {
+        *planet_seed = PlanetSeed(rng.stream(RngStream::Planet).gen());
+    }
+    info!("Starting cycle {}", cycle.0);
+    state.set(GameState::Playing);
+}
+
+fn reseed_rng(cycle: Res<Cycle>, mut rng: ResMut<GameRng>) {
+    rng.reseed(cycle.0);
+}
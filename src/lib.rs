@@ -1,16 +1,53 @@
 mod animation;
+mod aseprite;
+mod autotile;
+mod background;
+mod camera;
+mod colliders;
+mod cooldown;
+mod farm;
+mod grid;
+mod interaction;
+mod item;
+mod launch;
 mod loading;
 mod map;
+mod needs;
+mod palette;
 mod physics;
+mod pipes;
 mod player;
+mod power;
+mod rng;
+mod ship;
+mod skin;
+mod station;
 mod tank;
+mod terrain;
+mod tiled;
 mod ui;
 
 use crate::animation::SpriteAnimationPlugin;
+use crate::aseprite::AsepritePlugin;
+use crate::background::BackgroundPlugin;
+use crate::camera::CameraPlugin;
+use crate::colliders::ColliderPlugin;
+use crate::cooldown::CooldownPlugin;
+use crate::farm::FarmPlugin;
+use crate::interaction::InteractionPlugin;
+use crate::item::ItemPlugin;
+use crate::launch::LaunchPlugin;
 use crate::loading::LoadingPlugin;
 use crate::map::MapPlugin;
+use crate::needs::NeedsPlugin;
+use crate::pipes::PipePlugin;
 use crate::player::PlayerPlugin;
+use crate::power::PowerPlugin;
+use crate::ship::ShipPlugin;
+use crate::skin::SkinPlugin;
+use crate::station::StationPlugin;
 use crate::tank::TankPlugin;
+use crate::tiled::TiledPlugin;
 use crate::ui::UiPlugin;
 use avian2d::math::Vector;
 use avian2d::prelude::*;
@@ -20,6 +57,12 @@ use bevy::prelude::*;
 use bevy_tnua::prelude::*;
 use bevy_tnua_avian2d::TnuaAvian2dPlugin;
 
+pub use crate::camera::PixelScaling;
+pub use crate::map::Level;
+pub use crate::rng::{GameRng, RngStream};
+pub use crate::skin::ShipSkin;
+pub use crate::terrain::PlanetSeed;
+
 pub const WIDTH: f32 = 800.;
 pub const HEIGHT: f32 = 600.;
 
@@ -37,26 +80,38 @@ pub struct GamePlugin;
 impl Plugin for GamePlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<GameState>()
+            .init_resource::<GameRng>()
             .insert_resource(Gravity(Vector::Y * -98.1))
             .add_plugins((
                 PhysicsPlugins::default().with_length_unit(10.),
+                TnuaControllerPlugin::default(),
+                TnuaAvian2dPlugin::default(),
+                LaunchPlugin,
+                StationPlugin,
+                NeedsPlugin,
+                InteractionPlugin,
+                ItemPlugin,
+                CooldownPlugin,
+                PipePlugin,
+                PowerPlugin,
+                FarmPlugin,
+            ))
+            .add_plugins((
                 PlayerPlugin,
                 LoadingPlugin,
                 SpriteAnimationPlugin,
                 MapPlugin,
-                TnuaControllerPlugin::default(),
-                TnuaAvian2dPlugin::default(),
                 UiPlugin,
                 TankPlugin,
-            ))
-            .add_systems(Startup, spawn_camera);
+                ShipPlugin,
+                AsepritePlugin,
+                ColliderPlugin,
+                TiledPlugin,
+                CameraPlugin,
+                BackgroundPlugin,
+                SkinPlugin,
+            ));
         #[cfg(debug_assertions)]
         app.add_plugins(PhysicsDebugPlugin::default());
     }
 }
-
-fn spawn_camera(mut commands: Commands) {
-    let mut camera = Camera2dBundle::default();
-    camera.projection.scale = 0.5;
-    commands.spawn(camera);
-}
@@ -1,3 +1,7 @@
+use crate::aseprite::Aseprite;
+use crate::farm::CropConfig;
+use crate::ship::ShipLayout;
+use crate::tiled::TiledMap;
 use crate::GameState;
 use bevy::prelude::*;
 use bevy_asset_loader::prelude::*;
@@ -6,11 +10,14 @@ pub struct LoadingPlugin;
 
 impl Plugin for LoadingPlugin {
     fn build(&self, app: &mut App) {
-        app.add_loading_state(
-            LoadingState::new(GameState::Loading)
-                .load_collection::<ImageAssets>()
-                .continue_to_state(GameState::Playing),
-        );
+        let loading_state = LoadingState::new(GameState::Loading)
+            .load_collection::<ImageAssets>()
+            .load_collection::<LevelAssets>()
+            .continue_to_state(GameState::Menu);
+        // the `resources` asset source only exists on desktop
+        #[cfg(not(target_arch = "wasm32"))]
+        let loading_state = loading_state.load_collection::<AsepriteAssets>();
+        app.add_loading_state(loading_state);
     }
 }
 
@@ -24,7 +31,7 @@ pub struct ImageAssets {
     pub tilemap_character: Handle<Image>,
     #[asset(image(sampler = nearest))]
     #[asset(path = "tilemap-backgrounds.png")]
-    pub _tilemap_backgrounds: Handle<Image>,
+    pub tilemap_backgrounds: Handle<Image>,
     #[asset(image(sampler = nearest))]
     #[asset(path = "tilemap.png")]
     pub tilemap: Handle<Image>,
@@ -33,7 +40,7 @@ pub struct ImageAssets {
     pub tilemap_ship: Handle<Image>,
     #[asset(image(sampler = nearest))]
     #[asset(path = "tilemap-ship2.png")]
-    pub _tilemap_ship2: Handle<Image>,
+    pub tilemap_ship2: Handle<Image>,
 
     #[asset(texture_atlas_layout(
         tile_size_x = 24,
@@ -52,7 +59,7 @@ pub struct ImageAssets {
         padding_x = 1,
         padding_y = 1
     ))]
-    pub _tilemap_backgrounds_layout: Handle<TextureAtlasLayout>,
+    pub tilemap_backgrounds_layout: Handle<TextureAtlasLayout>,
     #[asset(texture_atlas_layout(
         tile_size_x = 18,
         tile_size_y = 18,
@@ -73,4 +80,24 @@ pub struct ImageAssets {
     pub tilemap_ship_layout: Handle<TextureAtlasLayout>,
 }
 
+#[derive(AssetCollection, Resource)]
+pub struct LevelAssets {
+    #[asset(path = "ship.ron")]
+    pub ship: Handle<ShipLayout>,
+    #[asset(path = "levels/ship.tmj")]
+    pub tiled: Handle<TiledMap>,
+    #[asset(path = "crops.ron")]
+    pub crops: Handle<CropConfig>,
+}
+
+/// Aseprite files in `resources/` that `map.png` and `tilemap-characters.png` are exported from;
+/// the game uses them directly where they are available, so they can be edited without exporting
+#[derive(AssetCollection, Resource)]
+pub struct AsepriteAssets {
+    #[asset(path = "resources://map.aseprite")]
+    pub map: Handle<Aseprite>,
+    #[asset(path = "resources://characters.aseprite")]
+    pub characters: Handle<Aseprite>,
+}
+
 pub const TILE_SIZE: f32 = 18.;
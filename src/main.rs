@@ -2,28 +2,97 @@
 mod menu;
 
 use crate::menu::MenuPlugin;
-use bevy_jam_5::{GamePlugin, GameState};
+use bevy_jam_5::{GamePlugin, GameRng, GameState};
 
 use bevy::prelude::*;
 use bevy_jam_5::{HEIGHT, WIDTH};
+use std::num::ParseIntError;
+use thiserror::Error;
 
-fn main() {
-    App::new()
-        .add_plugins((
-            DefaultPlugins
-                .set(ImagePlugin::default_nearest())
-                .set(WindowPlugin {
-                    primary_window: Some(Window {
-                        title: "Re-Cycles".to_string(),
-                        resolution: (WIDTH, HEIGHT).into(),
-                        canvas: Some("#bevy".to_owned()),
-                        resizable: false,
-                        ..default()
-                    }),
+fn main() -> AppExit {
+    let seed = match seed_argument(std::env::args()) {
+        Ok(seed) => seed,
+        Err(error) => {
+            eprintln!("{error}");
+            return AppExit::from_code(2);
+        }
+    };
+    let mut app = App::new();
+    if let Some(seed) = seed {
+        app.insert_resource(GameRng::new(seed));
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    add_resources_source(&mut app);
+    app.add_plugins((
+        DefaultPlugins
+            .set(ImagePlugin::default_nearest())
+            .set(WindowPlugin {
+                primary_window: Some(Window {
+                    title: "Re-Cycles".to_string(),
+                    resolution: (WIDTH, HEIGHT).into(),
+                    canvas: Some("#bevy".to_owned()),
+                    fit_canvas_to_parent: true,
                     ..default()
                 }),
-            MenuPlugin,
-            GamePlugin,
-        ))
-        .run();
+                ..default()
+            }),
+        MenuPlugin,
+        GamePlugin,
+    ))
+    .run()
+}
+
+#[derive(Debug, Error)]
+#[error("Invalid seed '{seed}': {source}")]
+struct SeedError {
+    seed: String,
+    source: ParseIntError,
+}
+
+/// Seed for all randomness from `--seed <number>`, to reproduce a run
+fn seed_argument(args: impl Iterator<Item = String>) -> Result<Option<u64>, SeedError> {
+    let mut args = args.skip_while(|arg| arg != "--seed");
+    if args.next().is_none() {
+        return Ok(None);
+    }
+    let seed = args.next().unwrap_or_default();
+    match seed.parse() {
+        Ok(seed) => Ok(Some(seed)),
+        Err(source) => Err(SeedError { seed, source }),
+    }
+}
+
+/// Reads the Aseprite files in `resources/` that the images are exported from;
+/// asset sources have to be registered before the `AssetPlugin` is added
+#[cfg(not(target_arch = "wasm32"))]
+fn add_resources_source(app: &mut App) {
+    use bevy::asset::io::AssetSource;
+    use std::time::Duration;
+
+    app.register_asset_source(
+        "resources",
+        AssetSource::build()
+            .with_reader(AssetSource::get_default_reader("resources".to_owned()))
+            .with_watcher(AssetSource::get_default_watcher(
+                "resources".to_owned(),
+                Duration::from_millis(300),
+            )),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn seed(args: &str) -> Result<Option<u64>, SeedError> {
+        seed_argument(args.split_whitespace().map(str::to_owned))
+    }
+
+    #[test]
+    fn reads_the_seed() {
+        assert_eq!(seed("game").unwrap(), None);
+        assert_eq!(seed("game --seed 42").unwrap(), Some(42));
+        assert_eq!(seed("game --seed abc").unwrap_err().seed, "abc");
+        assert_eq!(seed("game --seed").unwrap_err().seed, "");
+    }
 }
@@ -1,276 +1,504 @@
-use crate::loading::{ImageAssets, TILE_SIZE};
+use crate::aseprite::Aseprite;
+use crate::autotile;
+use crate::colliders::{CellShape, StaticCell};
+use crate::farm::PlotStates;
+use crate::grid::MapGrid;
+use crate::item::spawn_item;
+use crate::loading::{AsepriteAssets, ImageAssets, LevelAssets, TILE_SIZE};
+use crate::palette::{TileCollider, TileKind};
 use crate::physics::GameLayer;
-use crate::tank::FuelLevel;
-use crate::{GameState, HEIGHT, WIDTH};
+use crate::pipes::{PipeNode, Pipes, Pump};
+use crate::player::Player;
+use crate::power::{dim, insert_power, Consumer};
+use crate::rng::{GameRng, RngStream};
+use crate::ship::{FuelTankDefinition, ShipCollider, ShipLayout, ShipRole, ShipTileDefinition};
+use crate::skin::ShipSprite;
+use crate::tank::{FuelLevel, KeptTank, KeptTanks, TankStates};
+use crate::terrain::{generate_terrain, PlanetSeed};
+use crate::tiled::{spawn_tiled_items, spawn_tiled_map, TiledMap};
+use crate::GameState;
 use avian2d::prelude::*;
-use bevy::ecs::system::EntityCommands;
+use bevy::ecs::system::{EntityCommands, SystemParam};
 use bevy::prelude::*;
-use rand::{thread_rng, Rng};
+use rand::Rng;
 
 pub struct MapPlugin;
 
 impl Plugin for MapPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_map)
+        app.init_resource::<Level>()
+            .init_resource::<MapOrigin>()
+            .init_resource::<PlanetSeed>()
+            .add_systems(OnEnter(GameState::Playing), spawn_map)
+            .add_systems(OnExit(GameState::Playing), despawn_map)
             .add_systems(
                 Update,
-                (reload_map, toilet_sensor, update_fuel).run_if(in_state(GameState::Playing)),
+                (
+                    (reload_map, reload_ship).run_if(resource_equals(Level::Builtin)),
+                    toilet_sensor,
+                    spikes_sensor,
+                    update_fuel,
+                )
+                    .run_if(in_state(GameState::Playing)),
             );
     }
 }
 
-fn spawn_map(
+/// Which level is played; chosen in the menu
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Level {
+    /// Terrain from `map.png` and the ship from `ship.ron`
+    #[default]
+    Builtin,
+    /// `levels/ship.tmj` made with Tiled
+    Tiled,
+    /// Terrain generated from the `PlanetSeed` and the ship from `ship.ron`
+    Random,
+}
+
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn spawn_map(
     assets: Res<ImageAssets>,
+    level_assets: Res<LevelAssets>,
+    level: Res<Level>,
     mut commands: Commands,
-    images: Res<Assets<Image>>,
-    fuel_level: Res<FuelLevel>,
+    builtin_map: BuiltinMap,
+    ship_layouts: Res<Assets<ShipLayout>>,
+    tiled_maps: Res<Assets<TiledMap>>,
+    planet_seed: Res<PlanetSeed>,
+    mut rng: ResMut<GameRng>,
 ) {
-    let map = images.get(&assets.map).unwrap();
-    generate_map(map, &mut commands, &assets);
-    build_ship(&mut commands, &assets, &fuel_level);
+    let origin = match *level {
+        Level::Builtin => {
+            let mut grids = vec![];
+            for (name, image) in builtin_map.layers() {
+                match MapGrid::from_image(image) {
+                    Ok(grid) => grids.push(grid),
+                    Err(error) => error!("Failed to generate map layer '{name}': {error}"),
+                }
+            }
+            let origin = grids
+                .first()
+                .map_or_else(MapOrigin::default, MapOrigin::centered);
+            for grid in &grids {
+                generate_map(grid, &mut commands, &assets, origin);
+            }
+            let ship = ship_layouts.get(&level_assets.ship).unwrap();
+            build_ship(
+                &mut commands,
+                &assets,
+                ship,
+                &mut rng,
+                &KeptTanks::new(),
+                origin,
+            );
+            spawn_layout_items(&mut commands, &assets, ship, origin);
+            origin
+        }
+        Level::Tiled => {
+            let map = tiled_maps.get(&level_assets.tiled).unwrap();
+            let origin = MapOrigin::new(map.width, map.height);
+            spawn_tiled_map(map, &mut commands, &assets, &KeptTanks::new(), origin);
+            spawn_tiled_items(map, &mut commands, &assets, origin);
+            origin
+        }
+        Level::Random => {
+            info!("Generating planet {}", planet_seed.0);
+            let grid = generate_terrain(*planet_seed);
+            let origin = MapOrigin::centered(&grid);
+            generate_map(&grid, &mut commands, &assets, origin);
+            let ship = ship_layouts.get(&level_assets.ship).unwrap();
+            build_ship(
+                &mut commands,
+                &assets,
+                ship,
+                &mut rng,
+                &KeptTanks::new(),
+                origin,
+            );
+            spawn_layout_items(&mut commands, &assets, ship, origin);
+            origin
+        }
+    };
+    commands.insert_resource(origin);
+}
+
+/// Terrain of the built-in level: the layers of `map.aseprite` where it is loaded,
+/// otherwise the exported `map.png`
+#[derive(SystemParam)]
+pub(crate) struct BuiltinMap<'w> {
+    assets: Res<'w, ImageAssets>,
+    aseprite_assets: Option<Res<'w, AsepriteAssets>>,
+    images: Res<'w, Assets<Image>>,
+    aseprites: Res<'w, Assets<Aseprite>>,
+}
+
+impl BuiltinMap<'_> {
+    fn layers(&self) -> Vec<(&str, &Image)> {
+        let aseprite = self
+            .aseprite_assets
+            .as_ref()
+            .and_then(|aseprite_assets| self.aseprites.get(&aseprite_assets.map));
+        match aseprite {
+            Some(map) => map
+                .layers
+                .iter()
+                .map(|layer| (layer.name.as_str(), &layer.image))
+                .collect(),
+            None => vec![("map.png", self.images.get(&self.assets.map).unwrap())],
+        }
+    }
+}
+
+#[allow(clippy::type_complexity)]
+fn despawn_map(mut commands: Commands, tiles: Query<Entity, Or<(With<MapTile>, With<ShipTile>)>>) {
+    for entity in &tiles {
+        commands.entity(entity).despawn_recursive();
+    }
+    commands.remove_resource::<SpawnPoint>();
 }
 
 fn reload_map(
     assets: Res<ImageAssets>,
-    mut asset_reload: EventReader<AssetEvent<Image>>,
+    mut image_reload: EventReader<AssetEvent<Image>>,
+    mut aseprite_reload: EventReader<AssetEvent<Aseprite>>,
     mut commands: Commands,
-    images: Res<Assets<Image>>,
+    builtin_map: BuiltinMap,
     map_tiles: Query<Entity, With<MapTile>>,
+    origin: Res<MapOrigin>,
 ) {
-    for event in asset_reload.read() {
-        if event.is_modified(&assets.map) {
-            let map = images.get(&assets.map).unwrap();
-            for entity in &map_tiles {
-                commands.entity(entity).despawn_recursive();
-            }
-            generate_map(map, &mut commands, &assets);
+    let mut changed = false;
+    for event in image_reload.read() {
+        changed |= event.is_modified(&assets.map);
+    }
+    for event in aseprite_reload.read() {
+        changed |= builtin_map
+            .aseprite_assets
+            .as_ref()
+            .is_some_and(|aseprite_assets| event.is_modified(&aseprite_assets.map));
+    }
+    if !changed {
+        return;
+    }
+
+    let grids = match builtin_map
+        .layers()
+        .into_iter()
+        .map(|(_, image)| MapGrid::from_image(image))
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(grids) => grids,
+        Err(error) => {
+            error!("Failed to reload the map: {error}");
+            return;
         }
+    };
+    for entity in &map_tiles {
+        commands.entity(entity).despawn_recursive();
+    }
+    // the map stays where it started, even if its size changed
+    for grid in &grids {
+        generate_map(grid, &mut commands, &assets, *origin);
     }
 }
 
-fn generate_map(image: &Image, commands: &mut Commands, assets: &ImageAssets) {
-    for (tile, value) in image.data.iter().step_by(4).enumerate() {
-        let x = tile % 23;
-        let y = tile / 23;
+#[allow(clippy::too_many_arguments)]
+fn reload_ship(
+    assets: Res<ImageAssets>,
+    level_assets: Res<LevelAssets>,
+    mut ship_reload: EventReader<AssetEvent<ShipLayout>>,
+    mut atlas_reload: EventReader<AssetEvent<Image>>,
+    mut commands: Commands,
+    ship_layouts: Res<Assets<ShipLayout>>,
+    mut rng: ResMut<GameRng>,
+    ship_tiles: Query<Entity, With<ShipTile>>,
+    origin: Res<MapOrigin>,
+    tanks: TankStates,
+    plots: PlotStates,
+) {
+    let mut changed = false;
+    for event in ship_reload.read() {
+        changed |= event.is_modified(&level_assets.ship);
+    }
+    for event in atlas_reload.read() {
+        changed |=
+            event.is_modified(&assets.tilemap_ship) || event.is_modified(&assets.tilemap_ship2);
+    }
+    if !changed {
+        return;
+    }
 
-        if value == &1 {
-            commands.spawn(tile_bundle(x, y, assets));
-        }
+    // the layout only has the fuel and plants at the start
+    let kept = tanks.keep();
+    commands.insert_resource(plots.keep());
+    for entity in &ship_tiles {
+        commands.entity(entity).despawn_recursive();
     }
+    let ship = ship_layouts.get(&level_assets.ship).unwrap();
+    build_ship(&mut commands, &assets, ship, &mut rng, &kept, *origin);
 }
 
-fn build_ship(commands: &mut Commands, assets: &ImageAssets, fuel_level: &FuelLevel) {
-    // leg left
-    commands
-        .spawn(())
-        .spawn_ship_tile(107, 1, 14, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(75, 1, 13, assets, None)
-        .add_collider();
-    // leg right
-    commands
-        .spawn(())
-        .spawn_ship_tile(107, 21, 14, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(75, 21, 13, assets, None)
-        .add_collider();
-    // platform
-    commands
-        .spawn(())
-        .spawn_ship_tile(103, 1, 12, assets, None)
-        .add_collider();
-    for x in 2..21 {
-        let index = if thread_rng().gen_bool(1. / 3.) {
-            104
+fn generate_map(grid: &MapGrid, commands: &mut Commands, assets: &ImageAssets, origin: MapOrigin) {
+    for (x, y, kind) in grid.tiles() {
+        if kind == TileKind::SpawnPoint {
+            commands.insert_resource(SpawnPoint(origin.tile_position(x, y).truncate()));
         } else {
-            88
-        };
+            let index = autotile::atlas_index(grid, x, y, kind);
+            spawn_map_tile(commands, kind, index, (x, y), assets, origin);
+        }
+    }
+}
 
-        let mut entity = commands.spawn(());
-        entity.spawn_ship_tile(index, x, 12, assets, None);
-        if x != 11 {
-            entity.add_collider();
+/// Index of the tile, or of its variant if that is picked by chance
+fn variant_index(tile: &ShipTileDefinition, rng: &mut impl Rng) -> usize {
+    match &tile.variant {
+        Some(variant) if rng.gen_bool(variant.chance) => variant.index,
+        _ => tile.index,
+    }
+}
+
+fn build_ship(
+    commands: &mut Commands,
+    assets: &ImageAssets,
+    layout: &ShipLayout,
+    rng: &mut GameRng,
+    kept: &KeptTanks,
+    origin: MapOrigin,
+) {
+    let rng = rng.stream(RngStream::Ship);
+    for tile in &layout.tiles {
+        for (x, y) in tile.positions() {
+            let index = variant_index(tile, rng);
+
+            let mut entity = commands.spawn(ShipTile);
+            entity
+                .spawn_ship_tile(index, origin.tile_position(x, y), assets, tile.scale)
+                .on_layer(tile.layer);
+            match (tile.collider, tile.scale) {
+                (ShipCollider::None, _) => (),
+                (ShipCollider::Solid, None) => {
+                    entity.solid_cell(x, y);
+                }
+                (collider, scale) => {
+                    entity.add_collider();
+                    if let Some(scale) = scale {
+                        // colliders are scaled with the sprite, but should only cover one tile
+                        entity.insert(Collider::rectangle(
+                            TILE_SIZE / scale as f32,
+                            TILE_SIZE / scale as f32,
+                        ));
+                    }
+                    if collider == ShipCollider::Sensor {
+                        entity.insert(Sensor);
+                    }
+                }
+            }
+            match tile.role {
+                Some(ShipRole::Ladder) => {
+                    entity.insert(Ladder);
+                }
+                Some(ShipRole::Toilet) => {
+                    entity.insert(Toilet);
+                }
+                Some(ShipRole::TankInput) => {
+                    entity.insert(TankInput);
+                }
+                Some(ShipRole::Console) => {
+                    entity.insert(Console);
+                }
+                Some(ShipRole::Farm) => {
+                    entity.insert(Farm);
+                }
+                Some(ShipRole::Airlock) => {
+                    entity.insert(Airlock);
+                }
+                Some(ShipRole::WaterTap) => {
+                    entity.insert(WaterTap);
+                }
+                Some(ShipRole::Pump { rate }) => {
+                    entity.insert(Pump::new(rate));
+                }
+                Some(ShipRole::Engine) => {
+                    entity.insert(Engine);
+                }
+                None => (),
+            }
+            if let Some(node) = &tile.node {
+                entity.insert(PipeNode(node.clone()));
+            }
+            if let Some(power) = tile.power {
+                insert_power(&mut entity, power);
+            }
         }
     }
-    // exit ladders
-    commands
-        .spawn((Ladder, Sensor))
-        .spawn_ship_tile(11, 11, 12, assets, None)
-        .add_collider();
-    commands
-        .spawn((Ladder, Sensor))
-        .spawn_ship_tile(43, 11, 13, assets, None)
-        .add_collider();
-    commands
-        .spawn((Ladder, Sensor))
-        .spawn_ship_tile(43, 11, 14, assets, None)
-        .add_collider();
-
-    commands
-        .spawn(())
-        .spawn_ship_tile(106, 21, 12, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(107, 3, 13, assets, None)
-        .add_collider();
-    // side walls
-    for y in 0..12 {
-        let index = 58;
-        commands
-            .spawn(())
-            .spawn_ship_tile(index, 2, y, assets, None)
-            .add_collider();
-        commands
-            .spawn(())
-            .spawn_ship_tile(index, 20, y, assets, None)
-            .add_collider();
-    }
-    commands
-        .spawn(())
-        .spawn_ship_tile(8, 3, 0, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(24, 3, 1, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(40, 3, 2, assets, None)
-        .add_collider();
-    //toilet
-    commands
-        .spawn(())
-        .spawn_ship_tile(28, 3, 11, assets, Some(2))
-        .insert((
-            Sensor,
-            Toilet,
-            RigidBody::Static,
-            Collider::rectangle(TILE_SIZE / 2., TILE_SIZE / 2.),
-            CollisionLayers::new(GameLayer::Ground, GameLayer::Player),
-        ));
-    commands
-        .spawn(())
-        .spawn_ship_tile(12, 3, 9, assets, Some(2));
-    // farm
-    commands
-        .spawn(())
-        .spawn_ship_tile(17, 6, 11, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(18, 7, 11, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(19, 8, 11, assets, None)
-        .add_collider();
-
-    // tank
-    for y in 0..5 {
-        commands
-            .spawn(())
-            .spawn_ship_tile(75, 19, y, assets, None)
-            .add_collider();
-        commands
-            .spawn(())
-            .spawn_ship_tile(75, 18, y, assets, None)
-            .add_collider();
-    }
-    // tube exit
-    commands
-        .spawn(())
-        .spawn_ship_tile(79, 19, 5, assets, None)
-        .add_collider();
-    commands
-        .spawn(())
-        .spawn_ship_tile(79, 18, 5, assets, None)
-        .add_collider();
-    // fuel splash
-    render_fuel_tank(commands, assets, fuel_level);
-
-    // tank input
-    commands
-        .spawn((TankInput, Sensor))
-        .spawn_ship_tile(10, 17, 11, assets, None)
-        .add_collider();
+
+    for tank in &layout.fuel_tanks {
+        spawn_fuel_tank(commands, tank.clone(), assets, kept.get(&tank.name), origin)
+            .insert(ShipTile);
+    }
+    commands.insert_resource(Pipes(layout.pipes.clone()));
+}
+
+/// Only spawned when the level starts, so reloading the ship does not duplicate them
+fn spawn_layout_items(
+    commands: &mut Commands,
+    assets: &ImageAssets,
+    layout: &ShipLayout,
+    origin: MapOrigin,
+) {
+    for item in &layout.items {
+        let (x, y) = item.position;
+        spawn_item(
+            commands,
+            assets,
+            item.kind,
+            origin.tile_position(x, y).truncate(),
+        );
+    }
 }
 
 #[derive(Component)]
-struct MapTile;
+pub(crate) struct MapTile;
+/// Part of the ship; it is rebuilt when the layout is reloaded and lifts off when launching
 #[derive(Component)]
-struct Toilet;
+pub(crate) struct ShipTile;
+#[derive(Component)]
+pub(crate) struct Toilet;
+#[derive(Component)]
+pub(crate) struct Spikes;
 #[derive(Component)]
 pub(crate) struct Ladder;
 #[derive(Component)]
 pub(crate) struct TankInput;
+#[derive(Component)]
+pub(crate) struct Console;
+#[derive(Component)]
+pub(crate) struct Farm;
+#[derive(Component)]
+pub(crate) struct Airlock;
+#[derive(Component)]
+pub(crate) struct WaterTap;
+#[derive(Component)]
+pub(crate) struct Engine;
 
-fn tile_bundle(x: usize, y: usize, assets: &ImageAssets) -> impl Bundle {
-    (
-        SpriteBundle {
-            transform: Transform::from_xyz(
-                2. - WIDTH / 4. + TILE_SIZE * x as f32,
-                HEIGHT / 4. - TILE_SIZE * y as f32,
-                0.,
-            ),
-            texture: assets.tilemap.clone(),
-            ..default()
-        },
-        TextureAtlas {
-            layout: assets.tilemap_layout.clone(),
-            index: 122,
-        },
+/// Where the player enters the map
+#[derive(Resource)]
+pub(crate) struct SpawnPoint(pub(crate) Vec2);
+
+/// World position of the top left grid cell; terrain and ship share the same grid,
+/// which is centered on the world origin when the level is spawned
+#[derive(Resource, Clone, Copy, Default, Debug, PartialEq)]
+pub(crate) struct MapOrigin(Vec2);
+
+impl MapOrigin {
+    /// Origin of a map that is `width` x `height` tiles large
+    pub(crate) fn new(width: usize, height: usize) -> MapOrigin {
+        MapOrigin(Vec2::new(1. - width as f32, height as f32 - 1.) * TILE_SIZE / 2.)
+    }
+
+    pub(crate) fn centered(grid: &MapGrid) -> MapOrigin {
+        MapOrigin::new(grid.width, grid.height())
+    }
+
+    pub(crate) fn tile_position(&self, x: usize, y: usize) -> Vec3 {
+        Vec3::new(
+            self.0.x + TILE_SIZE * x as f32,
+            self.0.y - TILE_SIZE * y as f32,
+            0.,
+        )
+    }
+}
+
+fn spawn_map_tile(
+    commands: &mut Commands,
+    kind: TileKind,
+    index: Option<usize>,
+    (x, y): (usize, usize),
+    assets: &ImageAssets,
+    origin: MapOrigin,
+) {
+    let mut translation = origin.tile_position(x, y);
+    translation.z = kind.z();
+    let mut entity = commands.spawn((
         MapTile,
-        RigidBody::Static,
-        Collider::rectangle(TILE_SIZE, TILE_SIZE),
-        CollisionLayers::new(GameLayer::Ground, GameLayer::Player),
-    )
+        SpatialBundle::from_transform(Transform::from_translation(translation)),
+    ));
+    if let Some(index) = index {
+        entity.insert((
+            assets.tilemap.clone(),
+            Sprite {
+                color: kind.tint(),
+                ..default()
+            },
+            TextureAtlas {
+                layout: assets.tilemap_layout.clone(),
+                index,
+            },
+        ));
+    }
+    match kind.collider() {
+        TileCollider::None => (),
+        TileCollider::Solid => {
+            entity.solid_cell(x, y);
+        }
+        TileCollider::Platform => {
+            entity.insert(StaticCell {
+                x,
+                y,
+                shape: CellShape::Platform,
+            });
+        }
+        TileCollider::Sensor(size) => {
+            entity.insert((
+                Sensor,
+                RigidBody::Static,
+                Collider::rectangle(size.x, size.y),
+                CollisionLayers::new(GameLayer::Ground, GameLayer::Player),
+            ));
+        }
+    }
+    match kind {
+        TileKind::Spikes => {
+            entity.insert(Spikes);
+        }
+        TileKind::Ladder => {
+            entity.insert(Ladder);
+        }
+        _ => (),
+    }
 }
 
-trait MapCommand {
+pub(crate) trait MapCommand {
+    /// Tiles with a `scale` grow right and up from the cell at `translation`
     fn spawn_ship_tile(
         &mut self,
         index: usize,
-        x: usize,
-        y: usize,
+        translation: Vec3,
         assets: &ImageAssets,
         scale: Option<usize>,
     ) -> &mut Self;
     fn add_collider(&mut self) -> &mut Self;
+    /// Solid grid cell whose collider is merged with its neighbours
+    fn solid_cell(&mut self, x: usize, y: usize) -> &mut Self;
+    fn on_layer(&mut self, layer: u8) -> &mut Self;
 }
 
 impl MapCommand for EntityCommands<'_> {
     fn spawn_ship_tile(
         &mut self,
         index: usize,
-        x: usize,
-        y: usize,
+        translation: Vec3,
         assets: &ImageAssets,
         scale: Option<usize>,
     ) -> &mut Self {
         self.insert((
             SpriteBundle {
                 transform: {
-                    let mut transform = Transform::from_xyz(
-                        2. - WIDTH / 4. + TILE_SIZE * x as f32,
-                        HEIGHT / 4. - TILE_SIZE * y as f32,
-                        0.,
-                    );
+                    let mut transform = Transform::from_translation(translation);
                     if let Some(scale) = scale {
+                        let offset = TILE_SIZE * (scale as f32 - 1.) / 2.;
                         transform.scale = Vec3::splat(scale as f32);
-                        transform.translation = Vec3::new(
-                            2. - WIDTH / 4.
-                                + TILE_SIZE * (x - 1) as f32
-                                + (TILE_SIZE * scale as f32 + TILE_SIZE) / 2.,
-                            HEIGHT / 4.
-                                - TILE_SIZE * (y - 1) as f32
-                                - (TILE_SIZE * scale as f32 - TILE_SIZE) / 2.,
-                            0.,
-                        )
+                        transform.translation += Vec3::new(offset, offset, 0.);
                     }
                     transform
                 },
@@ -281,6 +509,7 @@ impl MapCommand for EntityCommands<'_> {
                 layout: assets.tilemap_ship_layout.clone(),
                 index,
             },
+            ShipSprite(index),
         ))
     }
 
@@ -291,58 +520,249 @@ impl MapCommand for EntityCommands<'_> {
             CollisionLayers::new(GameLayer::Ground, GameLayer::Player),
         ))
     }
+
+    fn solid_cell(&mut self, x: usize, y: usize) -> &mut Self {
+        self.insert(StaticCell {
+            x,
+            y,
+            shape: CellShape::Solid,
+        })
+    }
+
+    fn on_layer(&mut self, layer: u8) -> &mut Self {
+        self.add(move |mut entity: EntityWorldMut| {
+            if let Some(mut transform) = entity.get_mut::<Transform>() {
+                transform.translation.z = layer as f32 * 0.1;
+            }
+        })
+    }
 }
 
+/// Percent per second that the shown fuel level moves towards the actual level
+const FUEL_FILL_SPEED: f32 = 30.;
+/// Pixels the fuel surface moves up and down while sloshing at most
+const SLOSH_HEIGHT: f32 = 2.;
+
 fn update_fuel(
-    mut commands: Commands,
-    assets: Res<ImageAssets>,
-    fuel_level: Res<FuelLevel>,
-    fuel_tiles: Query<Entity, With<FuelTile>>,
+    time: Res<Time>,
+    mut fuel_tanks: Query<(&mut FuelTank, &FuelLevel, &Children)>,
+    mut parts: Query<(
+        &FuelPart,
+        &mut Transform,
+        Option<&mut Sprite>,
+        Option<&mut Collider>,
+    )>,
 ) {
-    if !fuel_level.is_changed() {
-        return;
-    }
-    for entity in &fuel_tiles {
-        commands.entity(entity).despawn_recursive();
+    let step = FUEL_FILL_SPEED * time.delta_seconds();
+    let elapsed = time.elapsed_seconds();
+    for (mut tank, fuel_level, children) in &mut fuel_tanks {
+        let difference = fuel_level.fraction() * 100. - tank.shown;
+        let resize_collider = difference != 0. || tank.is_added();
+        tank.shown += difference.clamp(-step, step);
+        // slosh harder while the level changes
+        let slosh = 0.5 + (difference.abs() / 10.).min(1.) * (SLOSH_HEIGHT - 0.5);
+
+        let (columns, rows) = tank.definition.size;
+        let bottom_left = tank.bottom_left;
+        let fuel_height = tank.shown / 100. * (rows - 1) as f32 * TILE_SIZE;
+        let mut parts = parts.iter_many_mut(children);
+        while let Some((part, mut transform, sprite, collider)) = parts.fetch_next() {
+            match (*part, sprite, collider) {
+                (FuelPart::Fuel { column, row }, Some(mut sprite), _) => {
+                    let bottom = row as f32 * TILE_SIZE;
+                    // only the part below the surface is drawn
+                    let visible = (fuel_height - bottom).clamp(0., TILE_SIZE);
+                    sprite.rect = Some(Rect::new(0., TILE_SIZE - visible, TILE_SIZE, TILE_SIZE));
+                    transform.translation.x = bottom_left.x + (column as f32 + 0.5) * TILE_SIZE;
+                    transform.translation.y = bottom_left.y + bottom + visible / 2.;
+                }
+                (FuelPart::Surface { column }, Some(mut sprite), _) => {
+                    let phase = elapsed * 4. + column as f32 * 1.5;
+                    let offset = (phase.sin() * slosh).round();
+                    // alternate the splash between columns and over time
+                    sprite.flip_x = (phase / std::f32::consts::PI) as i32 % 2 == 0;
+                    transform.translation.x = bottom_left.x + (column as f32 + 0.5) * TILE_SIZE;
+                    transform.translation.y = bottom_left.y + fuel_height + TILE_SIZE / 2. + offset;
+                }
+                (FuelPart::Collider, _, Some(mut collider)) if resize_collider => {
+                    // the surface is solid as well
+                    let size = Vec2::new(columns as f32 * TILE_SIZE, fuel_height + TILE_SIZE);
+                    *collider = Collider::rectangle(size.x, size.y);
+                    transform.translation = (bottom_left + size / 2.).extend(0.);
+                }
+                _ => (),
+            }
+        }
     }
-    render_fuel_tank(&mut commands, &assets, &fuel_level);
 }
 
+/// Shows the [`FuelLevel`] of the same entity; the fuel sprites and the collider are children
 #[derive(Component)]
-struct FuelTile;
-
-fn render_fuel_tank(commands: &mut Commands, assets: &ImageAssets, fuel_level: &FuelLevel) {
-    let full_blocks = (fuel_level.0.min(100.) / 20.) as usize;
-    let empty = 5 - full_blocks;
-
-    // splash
-    commands
-        .spawn(FuelTile)
-        .spawn_ship_tile(95, 19, 6 + empty, assets, None)
-        .add_collider();
-    commands
-        .spawn(FuelTile)
-        .spawn_ship_tile(95, 18, 6 + empty, assets, None)
-        .add_collider();
-    // fuel
-    for y in 6 + empty + 1..12 {
-        commands
-            .spawn(FuelTile)
-            .spawn_ship_tile(45, 19, y, assets, None)
-            .add_collider();
-        commands
-            .spawn(FuelTile)
-            .spawn_ship_tile(45, 18, y, assets, None)
-            .add_collider();
+pub(crate) struct FuelTank {
+    definition: FuelTankDefinition,
+    /// Bottom left corner of the tank interior
+    bottom_left: Vec2,
+    /// Fuel level that is currently shown; follows the actual level over time
+    shown: f32,
+}
+
+#[derive(Component, Clone, Copy)]
+enum FuelPart {
+    /// Counting rows from the bottom of the tank
+    Fuel {
+        column: usize,
+        row: usize,
+    },
+    /// Splash on top of the fuel
+    Surface {
+        column: usize,
+    },
+    Collider,
+}
+
+impl FuelTank {
+    /// World space area inside of the tank
+    pub(crate) fn interior(&self) -> Rect {
+        let (columns, rows) = self.definition.size;
+        Rect::from_corners(
+            self.bottom_left,
+            self.bottom_left + Vec2::new(columns as f32, rows as f32) * TILE_SIZE,
+        )
     }
 }
 
-fn toilet_sensor(mut query: Query<(&mut Sprite, &CollidingEntities), With<Toilet>>) {
-    for (mut sprite, colliding_entities) in &mut query {
-        if colliding_entities.0.is_empty() {
-            sprite.color = Color::srgb(0.2, 0.7, 0.9);
+/// Tanks that were `kept` before a reload get their fuel and leaks back
+pub(crate) fn spawn_fuel_tank<'a>(
+    commands: &'a mut Commands,
+    mut tank: FuelTankDefinition,
+    assets: &ImageAssets,
+    kept: Option<&KeptTank>,
+    origin: MapOrigin,
+) -> EntityCommands<'a> {
+    if let Some(kept) = kept {
+        tank.fuel = kept.amount.min(tank.capacity);
+    }
+    let (left, top) = tank.position;
+    let (columns, rows) = tank.size;
+    let mut entity = commands.spawn(SpatialBundle::default());
+    // positioned and cropped by `update_fuel`
+    entity.with_children(|parent| {
+        for column in 0..columns {
+            let translation = origin.tile_position(left + column, top);
+            parent.spawn(FuelPart::Surface { column }).spawn_ship_tile(
+                tank.surface_index,
+                translation,
+                assets,
+                None,
+            );
+            for row in 0..rows - 1 {
+                parent
+                    .spawn(FuelPart::Fuel { column, row })
+                    .spawn_ship_tile(tank.fuel_index, translation, assets, None);
+            }
+        }
+        // one collider for all the fuel, it changes too often to be merged with the map
+        parent
+            .spawn((FuelPart::Collider, TransformBundle::default()))
+            .add_collider()
+            .insert(CollisionLayers::new(
+                GameLayer::Ground,
+                [GameLayer::Player, GameLayer::Item],
+            ));
+        if let Some(kept) = kept {
+            kept.spawn_leaks(parent);
+        }
+    });
+    let fuel_level = FuelLevel::new(tank.fuel, tank.capacity);
+    entity.insert((
+        PipeNode(tank.name.clone()),
+        FuelTank {
+            shown: fuel_level.fraction() * 100.,
+            bottom_left: origin.tile_position(left, top + rows - 1).truncate()
+                - Vec2::splat(TILE_SIZE / 2.),
+            definition: tank,
+        },
+        fuel_level,
+    ));
+    entity
+}
+
+/// Toilets that need power are dimmed here instead of in `dim_consumers`
+fn toilet_sensor(
+    mut query: Query<(&mut Sprite, &CollidingEntities, Option<&Consumer>), With<Toilet>>,
+) {
+    for (mut sprite, colliding_entities, consumer) in &mut query {
+        let color = if colliding_entities.0.is_empty() {
+            Color::srgb(0.2, 0.7, 0.9)
         } else {
-            sprite.color = Color::srgb(0.9, 0.7, 0.2);
+            Color::srgb(0.9, 0.7, 0.2)
+        };
+        sprite.color = match consumer {
+            Some(consumer) if !consumer.powered => dim(color),
+            _ => color,
+        };
+    }
+}
+
+fn spikes_sensor(
+    spikes: Query<&CollidingEntities, With<Spikes>>,
+    spawn_point: Option<Res<SpawnPoint>>,
+    mut player: Query<(Entity, &mut Transform, &mut LinearVelocity), With<Player>>,
+) {
+    let Ok((player, mut transform, mut velocity)) = player.get_single_mut() else {
+        return;
+    };
+    if spikes
+        .iter()
+        .any(|colliding_entities| colliding_entities.contains(&player))
+    {
+        let spawn = spawn_point.map_or(Vec2::ZERO, |spawn_point| spawn_point.0);
+        transform.translation = spawn.extend(transform.translation.z);
+        velocity.0 = Vec2::ZERO;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn maps_are_centered() {
+        let origin = MapOrigin::new(23, 48);
+        assert_eq!(origin.tile_position(0, 0).x, -198.);
+        assert_eq!(origin.tile_position(0, 0), -origin.tile_position(22, 47));
+    }
+
+    /// Terrain and ship tiles of a cycle after the cycles before it used `used` random numbers
+    fn generate_cycle(seed: u64, cycle: u32, used: usize) -> (Vec<Option<TileKind>>, Vec<usize>) {
+        let layout: ShipLayout =
+            ron::de::from_bytes(&std::fs::read("assets/ship.ron").unwrap()).unwrap();
+        let mut rng = GameRng::new(seed);
+        for _ in 0..used {
+            rng.stream(RngStream::Ship).gen::<u64>();
+        }
+        rng.reseed(cycle);
+        let planet = PlanetSeed(rng.stream(RngStream::Planet).gen());
+        let terrain = generate_terrain(planet).cells;
+        let ship = rng.stream(RngStream::Ship);
+        let tiles = layout
+            .tiles
+            .iter()
+            .flat_map(|tile| {
+                tile.positions()
+                    .map(|_| variant_index(tile, ship))
+                    .collect::<Vec<_>>()
+            })
+            .collect();
+        (terrain, tiles)
+    }
+
+    #[test]
+    fn same_seed_generates_same_cycles() {
+        for cycle in 0..3 {
+            assert_eq!(generate_cycle(42, cycle, 0), generate_cycle(42, cycle, 100));
         }
+        assert_ne!(generate_cycle(42, 0, 0).0, generate_cycle(42, 1, 0).0);
+        assert_ne!(generate_cycle(42, 0, 0).0, generate_cycle(43, 0, 0).0);
     }
 }
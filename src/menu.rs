@@ -1,14 +1,26 @@
 use crate::GameState;
 use bevy::prelude::*;
+use bevy_jam_5::{GameRng, Level, PixelScaling, PlanetSeed, RngStream, ShipSkin};
+use rand::Rng;
 
 pub struct MenuPlugin;
 
-/// This plugin is responsible for the game menu (containing only one button...)
+/// This plugin is responsible for the game menu (containing one button per level)
 /// The menu is only drawn during the State `GameState::Menu` and is removed when that state is exited
 impl Plugin for MenuPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Menu), setup_menu)
-            .add_systems(Update, click_play_button.run_if(in_state(GameState::Menu)))
+            .add_systems(
+                Update,
+                (
+                    hover_buttons,
+                    click_play_button,
+                    click_skin_button,
+                    click_scaling_button,
+                    update_scaling_label,
+                )
+                    .run_if(in_state(GameState::Menu)),
+            )
             .add_systems(OnExit(GameState::Menu), cleanup_menu);
     }
 }
@@ -31,7 +43,7 @@ impl Default for ButtonColors {
 #[derive(Component)]
 struct Menu;
 
-fn setup_menu(mut commands: Commands) {
+fn setup_menu(mut commands: Commands, skin: Res<ShipSkin>, scaling: Res<PixelScaling>) {
     commands
         .spawn((
             NodeBundle {
@@ -48,37 +60,71 @@ fn setup_menu(mut commands: Commands) {
             Menu,
         ))
         .with_children(|children| {
-            let button_colors = ButtonColors::default();
-            children
-                .spawn((
-                    ButtonBundle {
-                        style: Style {
-                            width: Val::Px(140.0),
-                            height: Val::Px(50.0),
-                            justify_content: JustifyContent::Center,
-                            align_items: AlignItems::Center,
-                            ..Default::default()
-                        },
-                        background_color: button_colors.normal.into(),
-                        ..Default::default()
-                    },
-                    button_colors,
-                ))
-                .with_children(|parent| {
-                    parent.spawn(TextBundle::from_section(
-                        "Play",
-                        TextStyle {
-                            font_size: 40.0,
-                            color: Color::linear_rgb(0.9, 0.9, 0.9),
-                            ..default()
-                        },
-                    ));
-                });
+            for (label, level) in [
+                ("Play", Level::Builtin),
+                ("Tiled", Level::Tiled),
+                ("Random planet", Level::Random),
+            ] {
+                spawn_button(children, label, PlayButton(level));
+            }
+            spawn_button(children, &skin_label(*skin), SkinButton);
+            spawn_button(children, &scaling_label(*scaling), ScalingButton);
         });
 }
 
-fn click_play_button(
-    mut state: ResMut<NextState<GameState>>,
+fn spawn_button(children: &mut ChildBuilder, label: &str, button: impl Bundle) {
+    let button_colors = ButtonColors::default();
+    children
+        .spawn((
+            ButtonBundle {
+                style: Style {
+                    min_width: Val::Px(140.0),
+                    height: Val::Px(50.0),
+                    margin: UiRect::all(Val::Px(5.0)),
+                    padding: UiRect::horizontal(Val::Px(10.0)),
+                    justify_content: JustifyContent::Center,
+                    align_items: AlignItems::Center,
+                    ..Default::default()
+                },
+                background_color: button_colors.normal.into(),
+                ..Default::default()
+            },
+            button_colors,
+            button,
+        ))
+        .with_children(|parent| {
+            parent.spawn(TextBundle::from_section(
+                label,
+                TextStyle {
+                    font_size: 40.0,
+                    color: Color::linear_rgb(0.9, 0.9, 0.9),
+                    ..default()
+                },
+            ));
+        });
+}
+
+fn skin_label(skin: ShipSkin) -> String {
+    format!("Ship: {skin:?}")
+}
+
+fn scaling_label(scaling: PixelScaling) -> String {
+    format!("Scaling: {scaling:?} ({:?})", PixelScaling::KEY)
+}
+
+/// Starts the game with the given level
+#[derive(Component)]
+struct PlayButton(Level);
+
+/// Switches to the next ship skin
+#[derive(Component)]
+struct SkinButton;
+
+/// Switches between pixel perfect scaling and filling the window
+#[derive(Component)]
+struct ScalingButton;
+
+fn hover_buttons(
     mut interaction_query: Query<
         (&Interaction, &mut BackgroundColor, &ButtonColors),
         (Changed<Interaction>, With<Button>),
@@ -86,19 +132,75 @@ fn click_play_button(
 ) {
     for (interaction, mut color, button_colors) in &mut interaction_query {
         match *interaction {
-            Interaction::Pressed => {
-                state.set(GameState::Playing);
-            }
             Interaction::Hovered => {
                 *color = button_colors.hovered.into();
             }
             Interaction::None => {
                 *color = button_colors.normal.into();
             }
+            Interaction::Pressed => (),
+        }
+    }
+}
+
+fn click_play_button(
+    mut state: ResMut<NextState<GameState>>,
+    mut level: ResMut<Level>,
+    mut planet_seed: ResMut<PlanetSeed>,
+    mut rng: ResMut<GameRng>,
+    interaction_query: Query<(&Interaction, &PlayButton), Changed<Interaction>>,
+) {
+    for (interaction, play_button) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            *level = play_button.0;
+            if *level == Level::Random {
+                *planet_seed = PlanetSeed(rng.stream(RngStream::Planet).gen());
+            }
+            state.set(GameState::Playing);
+        }
+    }
+}
+
+fn click_skin_button(
+    mut skin: ResMut<ShipSkin>,
+    interaction_query: Query<(&Interaction, &Children), (Changed<Interaction>, With<SkinButton>)>,
+    mut text: Query<&mut Text>,
+) {
+    for (interaction, children) in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            *skin = skin.next();
+            let mut text = text.get_mut(children[0]).unwrap();
+            text.sections[0].value = skin_label(*skin);
+        }
+    }
+}
+
+fn click_scaling_button(
+    mut scaling: ResMut<PixelScaling>,
+    interaction_query: Query<&Interaction, (Changed<Interaction>, With<ScalingButton>)>,
+) {
+    for interaction in &interaction_query {
+        if *interaction == Interaction::Pressed {
+            *scaling = scaling.toggled();
         }
     }
 }
 
+/// The scaling can also be switched with its key while the menu is open
+fn update_scaling_label(
+    scaling: Res<PixelScaling>,
+    buttons: Query<&Children, With<ScalingButton>>,
+    mut text: Query<&mut Text>,
+) {
+    if !scaling.is_changed() {
+        return;
+    }
+    for children in &buttons {
+        let mut text = text.get_mut(children[0]).unwrap();
+        text.sections[0].value = scaling_label(*scaling);
+    }
+}
+
 fn cleanup_menu(mut commands: Commands, menu: Query<Entity, With<Menu>>) {
     for entity in menu.iter() {
         commands.entity(entity).despawn_recursive();
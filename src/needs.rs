@@ -0,0 +1,122 @@
+use crate::interaction::InteractionEvent;
+use crate::launch::Cycle;
+use crate::map::Airlock;
+use crate::player::Player;
+use crate::GameState;
+use bevy::prelude::*;
+
+pub struct NeedsPlugin;
+
+impl Plugin for NeedsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (decay_needs, use_airlock, pass_out)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// Something the player has to keep up with
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Need {
+    /// Restored by eating crops
+    Food,
+    /// Restored by using the toilet
+    Bladder,
+    /// Restored at the airlock
+    Oxygen,
+}
+
+impl Need {
+    pub const ALL: [Need; 3] = [Need::Food, Need::Bladder, Need::Oxygen];
+
+    /// Points lost per second
+    fn decay(self) -> f32 {
+        match self {
+            Need::Food => 0.5,
+            Need::Bladder => 0.8,
+            Need::Oxygen => 1.,
+        }
+    }
+}
+
+/// Meters between 0 and 100 that decay over time
+///
+/// The player slows down once a meter is low and passes out when one is empty.
+#[derive(Component)]
+pub struct Needs([f32; 3]);
+
+impl Default for Needs {
+    fn default() -> Self {
+        Needs([100.; 3])
+    }
+}
+
+impl Needs {
+    pub fn get(&self, need: Need) -> f32 {
+        self.0[need as usize]
+    }
+
+    pub fn restore(&mut self, need: Need, amount: f32) {
+        let value = &mut self.0[need as usize];
+        *value = (*value + amount).min(100.);
+    }
+
+    pub fn drain(&mut self, need: Need, amount: f32) {
+        let value = &mut self.0[need as usize];
+        *value = (*value - amount).max(0.);
+    }
+
+    /// Multiplier for the walking speed; 1 as long as no meter is low
+    pub fn speed_factor(&self) -> f32 {
+        let lowest = self.0.into_iter().fold(f32::MAX, f32::min);
+        MIN_SPEED_FACTOR.lerp(1., (lowest / LOW).min(1.))
+    }
+}
+
+/// Below this value a need slows the player down
+const LOW: f32 = 25.;
+/// Walking speed multiplier right before a need runs out
+const MIN_SPEED_FACTOR: f32 = 0.4;
+
+fn decay_needs(time: Res<Time>, mut needs: Query<&mut Needs>) {
+    for mut needs in &mut needs {
+        for need in Need::ALL {
+            needs.drain(need, need.decay() * time.delta_seconds());
+        }
+    }
+}
+
+fn use_airlock(
+    mut events: EventReader<InteractionEvent>,
+    airlocks: Query<(), With<Airlock>>,
+    mut player: Query<&mut Needs, With<Player>>,
+) {
+    let Ok(mut needs) = player.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        if airlocks.contains(event.entity) {
+            needs.restore(Need::Oxygen, 100.);
+        }
+    }
+}
+
+/// Ends the game once a need is empty; the next game starts at the first cycle and the player
+/// is spawned with full needs again
+fn pass_out(
+    needs: Query<&Needs, With<Player>>,
+    mut cycle: ResMut<Cycle>,
+    mut state: ResMut<NextState<GameState>>,
+) {
+    let Ok(needs) = needs.get_single() else {
+        return;
+    };
+    if let Some(need) = Need::ALL.into_iter().find(|&need| needs.get(need) <= 0.) {
+        info!("Passed out because of {need:?} in cycle {}", cycle.0);
+        cycle.0 = 0;
+        state.set(GameState::Menu);
+    }
+}
@@ -0,0 +1,103 @@
+use crate::loading::TILE_SIZE;
+use bevy::prelude::*;
+
+/// The different kinds of tiles that can be painted into `map.png`
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TileKind {
+    Ground,
+    Platform,
+    Spikes,
+    Ladder,
+    Decoration,
+    SpawnPoint,
+    Background,
+    /// Resources to mine, found in caves
+    Deposit,
+}
+
+/// Pixel colors (RGB) of the map image and the tile kind they stand for.
+/// Fully transparent and black pixels are empty cells; grayscale maps have no alpha,
+/// so black is their background.
+const PALETTE: [([u8; 3], TileKind); 9] = [
+    ([1, 90, 10], TileKind::Ground),
+    // older maps marked ground with a red value of 1
+    ([1, 0, 0], TileKind::Ground),
+    ([140, 90, 40], TileKind::Platform),
+    ([200, 200, 200], TileKind::Spikes),
+    ([180, 120, 40], TileKind::Ladder),
+    ([40, 180, 40], TileKind::Decoration),
+    ([255, 0, 0], TileKind::SpawnPoint),
+    ([80, 60, 40], TileKind::Background),
+    ([40, 120, 220], TileKind::Deposit),
+];
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum TileCollider {
+    None,
+    Solid,
+    /// Thin collider at the top of the tile
+    Platform,
+    Sensor(Vec2),
+}
+
+pub enum PaletteColor {
+    Empty,
+    Tile(TileKind),
+    Unknown,
+}
+
+impl PaletteColor {
+    pub fn from_rgba([red, green, blue, alpha]: [u8; 4]) -> PaletteColor {
+        if alpha == 0 || [red, green, blue] == [0, 0, 0] {
+            return PaletteColor::Empty;
+        }
+        PALETTE
+            .iter()
+            .find(|(color, _)| color == &[red, green, blue])
+            .map_or(PaletteColor::Unknown, |(_, kind)| PaletteColor::Tile(*kind))
+    }
+}
+
+impl TileKind {
+    /// Index in `tilemap.png`; `None` for tiles that are not drawn. Ground is autotiled instead.
+    pub fn atlas_index(&self) -> Option<usize> {
+        match self {
+            TileKind::Ground => Some(122),
+            TileKind::Platform => Some(146),
+            TileKind::Spikes => Some(68),
+            TileKind::Ladder => Some(51),
+            TileKind::Decoration => Some(124),
+            TileKind::SpawnPoint => None,
+            TileKind::Background => Some(104),
+            TileKind::Deposit => Some(67),
+        }
+    }
+
+    pub fn collider(&self) -> TileCollider {
+        match self {
+            TileKind::Ground => TileCollider::Solid,
+            TileKind::Platform => TileCollider::Platform,
+            TileKind::Spikes => TileCollider::Sensor(Vec2::new(TILE_SIZE, TILE_SIZE / 2.)),
+            TileKind::Ladder => TileCollider::Sensor(Vec2::splat(TILE_SIZE)),
+            TileKind::Decoration
+            | TileKind::SpawnPoint
+            | TileKind::Background
+            | TileKind::Deposit => TileCollider::None,
+        }
+    }
+
+    /// Background tiles are drawn darkened and behind everything else
+    pub fn tint(&self) -> Color {
+        match self {
+            TileKind::Background => Color::srgb(0.5, 0.5, 0.5),
+            _ => Color::WHITE,
+        }
+    }
+
+    pub fn z(&self) -> f32 {
+        match self {
+            TileKind::Background => -1.,
+            _ => 0.,
+        }
+    }
+}
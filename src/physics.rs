@@ -4,4 +4,5 @@ use avian2d::prelude::PhysicsLayer;
 pub enum GameLayer {
     Player,
     Ground,
+    Item,
 }
@@ -0,0 +1,162 @@
+use crate::interaction::{Interactable, InteractionEvent};
+use crate::ship::PipeDefinition;
+use crate::tank::FuelLevel;
+use crate::GameState;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+
+pub struct PipePlugin;
+
+impl Plugin for PipePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Pipes>()
+            .add_systems(
+                Update,
+                (toggle_pumps, run_pumps, equalize_tanks)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), clear_pipes);
+    }
+}
+
+/// Name of a fuel tank, tank input, pump or engine that pipes connect to
+#[derive(Component)]
+pub struct PipeNode(pub String);
+
+/// The pipes of the current level
+#[derive(Resource, Default)]
+pub struct Pipes(pub Vec<PipeDefinition>);
+
+impl Pipes {
+    /// Nodes the given node is piped to
+    pub fn downstream<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |pipe| pipe.from == node)
+            .map(|pipe| pipe.to.as_str())
+    }
+
+    /// Nodes that are piped into the given node
+    pub fn upstream<'a>(&'a self, node: &'a str) -> impl Iterator<Item = &'a str> + 'a {
+        self.0
+            .iter()
+            .filter(move |pipe| pipe.to == node)
+            .map(|pipe| pipe.from.as_str())
+    }
+}
+
+/// Finds the entities of pipe nodes by name
+#[derive(SystemParam)]
+pub struct PipeNodes<'w, 's> {
+    nodes: Query<'w, 's, (Entity, &'static PipeNode)>,
+}
+
+impl PipeNodes<'_, '_> {
+    pub fn get(&self, name: &str) -> Option<Entity> {
+        self.nodes
+            .iter()
+            .find(|(_, node)| node.0 == name)
+            .map(|(entity, _)| entity)
+    }
+}
+
+/// Moves fuel between tanks while it is running; switched on and off by the player
+#[derive(Component)]
+pub struct Pump {
+    /// Fuel per second
+    pub rate: f32,
+    pub running: bool,
+}
+
+impl Pump {
+    pub fn new(rate: f32) -> Self {
+        Pump {
+            rate,
+            running: false,
+        }
+    }
+
+    pub fn prompt(&self) -> &'static str {
+        if self.running {
+            "stop the pump"
+        } else {
+            "start the pump"
+        }
+    }
+}
+
+/// Fuel per second that flows through a pipe between two tanks at most
+const PIPE_FLOW_RATE: f32 = 5.;
+
+fn toggle_pumps(
+    mut events: EventReader<InteractionEvent>,
+    mut pumps: Query<(&mut Pump, &mut Interactable)>,
+) {
+    for event in events.read() {
+        if let Ok((mut pump, mut interactable)) = pumps.get_mut(event.entity) {
+            pump.running = !pump.running;
+            interactable.prompt = pump.prompt().to_owned();
+        }
+    }
+}
+
+/// Running pumps take fuel from the first tank piped into them that has some and put it into
+/// the first tank they are piped to that has room
+fn run_pumps(
+    time: Res<Time>,
+    pipes: Res<Pipes>,
+    nodes: PipeNodes,
+    pumps: Query<(&Pump, &PipeNode)>,
+    mut tanks: Query<&mut FuelLevel>,
+) {
+    for (pump, node) in &pumps {
+        if !pump.running {
+            continue;
+        }
+        let source = pipes
+            .upstream(&node.0)
+            .filter_map(|name| nodes.get(name))
+            .find(|&entity| tanks.get(entity).is_ok_and(|tank| tank.amount > 0.));
+        let target = pipes
+            .downstream(&node.0)
+            .filter_map(|name| nodes.get(name))
+            .find(|&entity| tanks.get(entity).is_ok_and(|tank| !tank.is_full()));
+        let (Some(source), Some(target)) = (source, target) else {
+            continue;
+        };
+        let Ok([mut source, mut target]) = tanks.get_many_mut([source, target]) else {
+            continue;
+        };
+        let amount = (pump.rate * time.delta_seconds()).min(source.amount);
+        source.amount -= amount - target.fill(amount);
+    }
+}
+
+/// Lets fuel flow through pipes between tanks until both are filled to the same level
+fn equalize_tanks(
+    time: Res<Time>,
+    pipes: Res<Pipes>,
+    nodes: PipeNodes,
+    mut tanks: Query<&mut FuelLevel>,
+) {
+    let max_flow = PIPE_FLOW_RATE * time.delta_seconds();
+    for pipe in &pipes.0 {
+        let (Some(from), Some(to)) = (nodes.get(&pipe.from), nodes.get(&pipe.to)) else {
+            continue;
+        };
+        let Ok([mut from, mut to]) = tanks.get_many_mut([from, to]) else {
+            continue;
+        };
+        // fuel that has to move for both tanks to have the same fraction
+        let difference =
+            (from.amount * to.capacity - to.amount * from.capacity) / (from.capacity + to.capacity);
+        let flow = difference.clamp(-max_flow, max_flow);
+        from.amount -= flow;
+        to.amount += flow;
+    }
+}
+
+fn clear_pipes(mut pipes: ResMut<Pipes>) {
+    pipes.0.clear();
+}
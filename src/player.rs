@@ -1,9 +1,13 @@
-use crate::animation::{AnimationIndices, AnimationTimer};
-use crate::loading::ImageAssets;
+use crate::animation::{AnimationIndices, AnimationTimer, AsepriteAnimation};
+use crate::item::Inventory;
+use crate::loading::{AsepriteAssets, ImageAssets};
+use crate::map::{spawn_map, SpawnPoint};
+use crate::needs::Needs;
+use crate::physics::GameLayer;
 use crate::GameState;
 use avian2d::collision::{Collider, CollidingEntities};
 use avian2d::math::AdjustPrecision;
-use avian2d::prelude::RigidBody;
+use avian2d::prelude::{CollisionLayers, RigidBody};
 use bevy::prelude::*;
 use bevy::time::Stopwatch;
 use bevy_tnua::builtins::{TnuaBuiltinJump, TnuaBuiltinWalk};
@@ -18,19 +22,37 @@ pub struct PlayerPlugin;
 
 impl Plugin for PlayerPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(OnEnter(GameState::Playing), spawn_player)
+        app.add_systems(OnEnter(GameState::Playing), spawn_player.after(spawn_map))
+            .add_systems(OnExit(GameState::Playing), despawn_player)
             .add_systems(Update, apply_controls.in_set(TnuaUserControlsSystemSet));
     }
 }
 
-fn spawn_player(mut commands: Commands, asset: Res<ImageAssets>) {
-    commands.spawn((
+#[derive(Component)]
+pub(crate) struct Player;
+
+/// Horizontal direction the player looked at last; 1 is right and -1 is left
+#[derive(Component)]
+pub(crate) struct Facing(pub(crate) f32);
+
+/// Tag in `characters.aseprite`
+const PLAYER_ANIMATION: &str = "player";
+
+fn spawn_player(
+    mut commands: Commands,
+    assets: Res<ImageAssets>,
+    aseprite_assets: Option<Res<AsepriteAssets>>,
+    spawn_point: Option<Res<SpawnPoint>>,
+) {
+    let spawn = spawn_point.map_or(Vec2::ZERO, |spawn_point| spawn_point.0);
+    let mut player = commands.spawn((
         SpriteBundle {
-            texture: asset.tilemap_character.clone(),
+            texture: assets.tilemap_character.clone(),
+            transform: Transform::from_translation(spawn.extend(0.)),
             ..default()
         },
         TextureAtlas {
-            layout: asset.tilemap_character_layout.clone(),
+            layout: assets.tilemap_character_layout.clone(),
             index: 5,
         },
         AnimationTimer(Timer::new(Duration::from_millis(300), TimerMode::Repeating)),
@@ -38,15 +60,32 @@ fn spawn_player(mut commands: Commands, asset: Res<ImageAssets>) {
         Collider::capsule(8., 6.0),
         TnuaControllerBundle::default(),
         RigidBody::Dynamic,
+        Player,
+        Needs::default(),
+        Inventory::default(),
+        Facing(1.),
+        CollisionLayers::new(GameLayer::Player, GameLayer::Ground),
     ));
+    if let Some(aseprite_assets) = aseprite_assets {
+        player.insert(AsepriteAnimation {
+            aseprite: aseprite_assets.characters.clone(),
+            tag: PLAYER_ANIMATION.to_owned(),
+        });
+    }
+}
+
+fn despawn_player(mut commands: Commands, player: Query<Entity, With<Player>>) {
+    for entity in &player {
+        commands.entity(entity).despawn_recursive();
+    }
 }
 
 fn apply_controls(
     ladders: Query<&CollidingEntities, With<crate::map::Ladder>>,
     keyboard: Res<ButtonInput<KeyCode>>,
-    mut player: Query<&mut TnuaController>,
+    mut player: Query<(&mut TnuaController, &Needs, &mut Facing)>,
 ) {
-    let Ok(mut controller) = player.get_single_mut() else {
+    let Ok((mut controller, needs, mut facing)) = player.get_single_mut() else {
         return;
     };
 
@@ -65,12 +104,16 @@ fn apply_controls(
         direction += Vec3::X;
     }
 
+    if direction.x != 0. {
+        facing.0 = direction.x.signum();
+    }
+
     // Feed the basis every frame. Even if the player doesn't move - just use `desired_velocity:
     // Vec3::ZERO`. `TnuaController` starts without a basis, which will make the character collider
     // just fall.
     controller.basis(TnuaBuiltinWalk {
         // The `desired_velocity` determines how the character will move.
-        desired_velocity: direction.normalize_or_zero() * 200.0,
+        desired_velocity: direction.normalize_or_zero() * 200.0 * needs.speed_factor(),
         // The `float_height` must be greater (even if by little) from the distance between the
         // character's center and the lowest point of its collider.
         float_height: 12.,
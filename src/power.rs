@@ -0,0 +1,227 @@
+use crate::map::Toilet;
+use crate::needs::{Need, Needs};
+use crate::pipes::{PipeNode, PipeNodes, Pipes};
+use crate::player::Player;
+use crate::ship::{ConsumerKind, PowerDefinition};
+use crate::tank::FuelLevel;
+use crate::GameState;
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use std::cmp::Reverse;
+
+pub struct PowerPlugin;
+
+impl Plugin for PowerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PowerGrid>()
+            .add_systems(OnEnter(GameState::Playing), prep_power)
+            .add_systems(
+                Update,
+                (balance_power, dim_consumers, life_support)
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            );
+    }
+}
+
+/// Totals of the power grid, updated every frame
+#[derive(Resource, Default, PartialEq, Debug)]
+pub struct PowerGrid {
+    /// Power that solar panels and generators with fuel can deliver
+    pub supply: f32,
+    /// Power that all consumers together ask for
+    pub demand: f32,
+    /// Energy in all batteries
+    pub stored: f32,
+    pub capacity: f32,
+}
+
+/// Turns fuel into power; only burns as much as is needed
+#[derive(Component)]
+pub struct Generator {
+    pub output: f32,
+    /// Fuel burnt per second at full output
+    pub fuel_per_second: f32,
+}
+
+#[derive(Component)]
+pub struct SolarPanel {
+    pub output: f32,
+}
+
+/// Stores surplus power of the solar panels and steps in when there is not enough
+#[derive(Component)]
+pub struct Battery {
+    pub charge: f32,
+    pub capacity: f32,
+}
+
+/// Needs power to work; see [`ConsumerKind`] for what each kind does
+#[derive(Component)]
+pub struct Consumer {
+    pub kind: ConsumerKind,
+    pub demand: f32,
+    /// Consumers with a lower priority are switched off first
+    pub priority: u8,
+    pub powered: bool,
+}
+
+pub(crate) fn insert_power(entity: &mut EntityCommands, definition: PowerDefinition) {
+    match definition {
+        PowerDefinition::Generator {
+            output,
+            fuel_per_second,
+        } => entity.insert(Generator {
+            output,
+            fuel_per_second,
+        }),
+        PowerDefinition::SolarPanel { output } => entity.insert(SolarPanel { output }),
+        PowerDefinition::Battery { capacity } => entity.insert(Battery {
+            charge: capacity,
+            capacity,
+        }),
+        PowerDefinition::Consumer {
+            kind,
+            demand,
+            priority,
+        } => entity.insert(Consumer {
+            kind,
+            demand,
+            priority,
+            powered: true,
+        }),
+    };
+}
+
+/// Oxygen per second lost while the life support is off
+const LIFE_SUPPORT_FAILURE: f32 = 2.;
+const UNPOWERED_COLOR: Color = Color::srgb(0.35, 0.35, 0.4);
+
+fn prep_power(mut grid: ResMut<PowerGrid>) {
+    *grid = PowerGrid::default();
+}
+
+/// Powers consumers by priority and takes the power from solar panels, generators and
+/// batteries in this order; surplus solar power charges the batteries
+#[allow(clippy::too_many_arguments)]
+fn balance_power(
+    time: Res<Time>,
+    mut grid: ResMut<PowerGrid>,
+    pipes: Res<Pipes>,
+    nodes: PipeNodes,
+    generators: Query<(&Generator, &PipeNode)>,
+    solar_panels: Query<&SolarPanel>,
+    mut batteries: Query<&mut Battery>,
+    mut consumers: Query<&mut Consumer>,
+    mut tanks: Query<&mut FuelLevel>,
+) {
+    let delta = time.delta_seconds();
+    if delta <= 0. {
+        return;
+    }
+    let solar: f32 = solar_panels.iter().map(|panel| panel.output).sum();
+    // generators with fuel and the tank they burn it from
+    let fueled: Vec<(&Generator, Entity)> = generators
+        .iter()
+        .filter_map(|(generator, node)| {
+            let tank = pipes
+                .upstream(&node.0)
+                .filter_map(|name| nodes.get(name))
+                .find(|&entity| tanks.get(entity).is_ok_and(|tank| tank.amount > 0.))?;
+            Some((generator, tank))
+        })
+        .collect();
+    let generated: f32 = fueled.iter().map(|(generator, _)| generator.output).sum();
+    let stored: f32 = batteries.iter().map(|battery| battery.charge).sum();
+
+    // brownout: once a consumer gets too little power, all with a lower priority are off as well
+    let mut consumers: Vec<_> = consumers.iter_mut().collect();
+    consumers.sort_by_key(|consumer| Reverse(consumer.priority));
+    let mut available = solar + generated + stored / delta;
+    let (mut demand, mut used) = (0., 0.);
+    let mut brownout = false;
+    for consumer in &mut consumers {
+        demand += consumer.demand;
+        brownout |= consumer.demand > available;
+        if !brownout {
+            available -= consumer.demand;
+            used += consumer.demand;
+        }
+        let powered = !brownout;
+        if consumer.powered != powered {
+            consumer.powered = powered;
+        }
+    }
+
+    let from_solar = used.min(solar);
+    let from_generators = (used - from_solar).min(generated);
+    if generated > 0. {
+        let load = from_generators / generated;
+        for &(generator, tank) in &fueled {
+            if let Ok(mut tank) = tanks.get_mut(tank) {
+                let burnt = generator.fuel_per_second * load * delta;
+                tank.amount = (tank.amount - burnt).max(0.);
+            }
+        }
+    }
+    let mut discharge = (used - from_solar - from_generators) * delta;
+    let mut surplus = (solar - from_solar) * delta;
+    let (mut remaining, mut capacity) = (0., 0.);
+    for mut battery in &mut batteries {
+        let taken = discharge.min(battery.charge);
+        let added = surplus.min(battery.capacity - battery.charge + taken);
+        battery.charge += added - taken;
+        discharge -= taken;
+        surplus -= added;
+        remaining += battery.charge;
+        capacity += battery.capacity;
+    }
+
+    grid.set_if_neq(PowerGrid {
+        supply: solar + generated,
+        demand,
+        stored: remaining,
+        capacity,
+    });
+}
+
+/// Darkens the color of a consumer without power
+pub(crate) fn dim(color: Color) -> Color {
+    let (color, dimmed) = (color.to_srgba(), UNPOWERED_COLOR.to_srgba());
+    Color::srgba(
+        color.red * dimmed.red,
+        color.green * dimmed.green,
+        color.blue * dimmed.blue,
+        color.alpha,
+    )
+}
+
+/// Toilets have a color of their own, they are dimmed by `toilet_sensor`
+#[allow(clippy::type_complexity)]
+fn dim_consumers(
+    mut consumers: Query<(&Consumer, &mut Sprite), (Changed<Consumer>, Without<Toilet>)>,
+) {
+    for (consumer, mut sprite) in &mut consumers {
+        sprite.color = if consumer.powered {
+            Color::WHITE
+        } else {
+            UNPOWERED_COLOR
+        };
+    }
+}
+
+fn life_support(
+    time: Res<Time>,
+    consumers: Query<&Consumer>,
+    mut player: Query<&mut Needs, With<Player>>,
+) {
+    let failing = consumers
+        .iter()
+        .any(|consumer| consumer.kind == ConsumerKind::LifeSupport && !consumer.powered);
+    if !failing {
+        return;
+    }
+    for mut needs in &mut player {
+        needs.drain(Need::Oxygen, LIFE_SUPPORT_FAILURE * time.delta_seconds());
+    }
+}
@@ -0,0 +1,64 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Source of all randomness in the game. The same seed always produces the same worlds.
+///
+/// Every subsystem draws from its own stream, so using more random numbers in one place does not
+/// change the results of another. The streams start over in every cycle.
+#[derive(Resource, Debug)]
+pub struct GameRng {
+    seed: u64,
+    cycle: u32,
+    streams: HashMap<RngStream, ChaCha8Rng>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum RngStream {
+    Ship,
+    Planet,
+    /// Visual effects like particles and camera shake
+    Effects,
+    /// Damage to the ship
+    Hazards,
+}
+
+impl GameRng {
+    pub fn new(seed: u64) -> Self {
+        GameRng {
+            seed,
+            cycle: 0,
+            streams: HashMap::new(),
+        }
+    }
+
+    /// Restarts all streams for the given cycle, so a cycle plays out the same for the same seed
+    /// no matter how many random numbers the cycles before it used
+    pub fn reseed(&mut self, cycle: u32) {
+        self.cycle = cycle;
+        self.streams.clear();
+    }
+
+    pub fn seed(&self) -> u64 {
+        self.seed
+    }
+
+    pub fn stream(&mut self, stream: RngStream) -> &mut ChaCha8Rng {
+        let (seed, cycle) = (self.seed, self.cycle);
+        self.streams.entry(stream).or_insert_with(|| {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            rng.set_stream((cycle as u64) << 32 | stream as u64);
+            rng
+        })
+    }
+}
+
+impl Default for GameRng {
+    /// Random seed; it is logged so the run can be reproduced with `--seed`
+    fn default() -> Self {
+        let seed = rand::random();
+        info!("Random seed {seed}");
+        GameRng::new(seed)
+    }
+}
@@ -0,0 +1,549 @@
+use crate::item::ItemKind;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::prelude::*;
+use bevy::utils::{HashMap, HashSet};
+use serde::Deserialize;
+use thiserror::Error;
+
+/// Number of tiles in `tilemap-ship.png` (16 columns, 7 rows)
+pub const SHIP_TILE_COUNT: usize = 16 * 7;
+
+pub struct ShipPlugin;
+
+impl Plugin for ShipPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<ShipLayout>()
+            .init_asset_loader::<ShipLayoutLoader>();
+    }
+}
+
+/// The ship as described in `assets/ship.ron`
+#[derive(Asset, TypePath, Deserialize, Debug)]
+pub struct ShipLayout {
+    pub tiles: Vec<ShipTileDefinition>,
+    pub fuel_tanks: Vec<FuelTankDefinition>,
+    /// Connections between fuel tanks and tiles with a `node` name
+    #[serde(default)]
+    pub pipes: Vec<PipeDefinition>,
+    /// Items lying around in the ship when the level starts
+    #[serde(default)]
+    pub items: Vec<ItemDefinition>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ShipTileDefinition {
+    /// Index into the ship tile atlas
+    pub index: usize,
+    /// Grid position of the tile; for scaled tiles this is the bottom left cell
+    pub position: (usize, usize),
+    /// Repeat the tile over this many columns and rows starting at `position`
+    #[serde(default = "single_cell")]
+    pub repeat: (usize, usize),
+    pub scale: Option<usize>,
+    #[serde(default)]
+    pub collider: ShipCollider,
+    pub role: Option<ShipRole>,
+    /// Tiles on a higher layer are drawn on top and may overlap tiles on other layers
+    #[serde(default)]
+    pub layer: u8,
+    /// Randomly replaces the tile index with another one
+    pub variant: Option<TileVariant>,
+    /// Name that pipes use to connect to this tile, e.g. for tank inputs, pumps and engines
+    pub node: Option<String>,
+    /// Part of the power grid
+    pub power: Option<PowerDefinition>,
+}
+
+#[derive(Deserialize, Debug)]
+pub struct ItemDefinition {
+    pub kind: ItemKind,
+    /// Grid position of the item
+    pub position: (usize, usize),
+}
+
+fn single_cell() -> (usize, usize) {
+    (1, 1)
+}
+
+impl ShipTileDefinition {
+    pub fn positions(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.repeat.1).flat_map(move |row| {
+            (0..self.repeat.0).map(move |column| (self.position.0 + column, self.position.1 + row))
+        })
+    }
+
+    /// All grid cells covered by the tile at the given position
+    fn cells(&self, (x, y): (usize, usize)) -> impl Iterator<Item = (usize, usize)> {
+        let scale = self.scale.unwrap_or(1);
+        (0..scale)
+            .flat_map(move |row| (0..scale).map(move |column| (x + column, y + 1 - scale + row)))
+    }
+}
+
+#[derive(Deserialize, Debug, Default, Clone, Copy, PartialEq)]
+pub enum ShipCollider {
+    #[default]
+    None,
+    Solid,
+    Sensor,
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum ShipRole {
+    Ladder,
+    Toilet,
+    TankInput,
+    /// Launches the ship once the tanks that feed the engines are full
+    Console,
+    /// Farm plot; crops grow on it with water, faster with waste as fertilizer
+    Farm,
+    /// Refills the player's oxygen
+    Airlock,
+    /// Gives water for the farm
+    WaterTap,
+    /// Moves fuel per second from the tanks piped into it to the tanks it is piped to
+    Pump {
+        rate: f32,
+    },
+    /// Burns the fuel of the tanks piped into it when launching
+    Engine,
+}
+
+/// How a tile takes part in the ship's power grid
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+pub enum PowerDefinition {
+    /// Burns fuel per second from the tanks piped into it while running at full output
+    Generator {
+        output: f32,
+        fuel_per_second: f32,
+    },
+    SolarPanel {
+        output: f32,
+    },
+    /// Starts fully charged
+    Battery {
+        capacity: f32,
+    },
+    /// Consumers with a lower priority are switched off first when there is not enough power
+    Consumer {
+        kind: ConsumerKind,
+        demand: f32,
+        priority: u8,
+    },
+}
+
+impl PowerDefinition {
+    /// Generators burn the fuel of the tanks piped to them, so they need a node name
+    pub fn is_valid(&self, has_node: bool) -> bool {
+        match *self {
+            PowerDefinition::Generator {
+                output,
+                fuel_per_second,
+            } => has_node && output > 0. && fuel_per_second >= 0.,
+            PowerDefinition::SolarPanel { output } => output > 0.,
+            PowerDefinition::Battery { capacity } => capacity > 0.,
+            PowerDefinition::Consumer { demand, .. } => demand >= 0.,
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConsumerKind {
+    Light,
+    /// Farms only grow crops while their lamps are on
+    FarmLamp,
+    /// The player loses oxygen faster without it
+    LifeSupport,
+}
+
+impl ConsumerKind {
+    pub const ALL: [ConsumerKind; 3] = [
+        ConsumerKind::Light,
+        ConsumerKind::FarmLamp,
+        ConsumerKind::LifeSupport,
+    ];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            ConsumerKind::Light => "Lights",
+            ConsumerKind::FarmLamp => "Farm lamps",
+            ConsumerKind::LifeSupport => "Life support",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug)]
+pub struct TileVariant {
+    pub index: usize,
+    pub chance: f64,
+}
+
+/// Fuel tank that shows its current fuel level
+#[derive(Deserialize, Debug, Clone)]
+pub struct FuelTankDefinition {
+    /// Name that pipes use to connect to the tank
+    pub name: String,
+    /// Top left cell of the tank interior
+    pub position: (usize, usize),
+    /// Columns and rows of the tank interior; the top row is reserved for the fuel surface
+    pub size: (usize, usize),
+    pub fuel_index: usize,
+    pub surface_index: usize,
+    #[serde(default = "default_tank_capacity")]
+    pub capacity: f32,
+    /// Fuel in the tank when the level starts
+    #[serde(default)]
+    pub fuel: f32,
+}
+
+pub const DEFAULT_TANK_CAPACITY: f32 = 100.;
+
+fn default_tank_capacity() -> f32 {
+    DEFAULT_TANK_CAPACITY
+}
+
+/// Connects two pipe nodes
+///
+/// Fuel flows both ways between two tanks until they are filled to the same level. Pumps and
+/// engines take fuel from the tanks piped into them, tank inputs and pumps deliver fuel to the
+/// tanks they are piped to.
+#[derive(Deserialize, Debug, Clone)]
+pub struct PipeDefinition {
+    pub from: String,
+    pub to: String,
+}
+
+impl FuelTankDefinition {
+    /// Tanks need a column, a row below the fuel surface and room for their fuel
+    pub fn is_valid(&self) -> bool {
+        self.size.0 > 0
+            && self.size.1 >= 2
+            && self.capacity > 0.
+            && (0. ..=self.capacity).contains(&self.fuel)
+    }
+
+    pub fn cells(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.size.1).flat_map(move |row| {
+            (0..self.size.0).map(move |column| (self.position.0 + column, self.position.1 + row))
+        })
+    }
+}
+
+#[derive(Default)]
+struct ShipLayoutLoader;
+
+#[derive(Debug, Error)]
+pub enum ShipLayoutError {
+    #[error("Could not read ship layout: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse ship layout: {0}")]
+    Ron(#[from] ron::error::SpannedError),
+    #[error("Tile index {index} at {position:?} is outside of the ship tile atlas ({SHIP_TILE_COUNT} tiles)")]
+    InvalidTileIndex {
+        index: usize,
+        position: (usize, usize),
+    },
+    #[error("Variant chance {chance} at {position:?} has to be between 0 and 1")]
+    InvalidChance {
+        chance: f64,
+        position: (usize, usize),
+    },
+    #[error("Tile scale at {0:?} has to be at least 1")]
+    InvalidScale((usize, usize)),
+    #[error("Scaled tile at {0:?} reaches above the top of the ship")]
+    OutOfBounds((usize, usize)),
+    #[error("Tiles with indices {first} and {second} overlap at {cell:?} on layer {layer}")]
+    Overlap {
+        cell: (usize, usize),
+        layer: u8,
+        first: usize,
+        second: usize,
+    },
+    #[error("Fuel tank '{0}' needs at least one column and two rows and more capacity than fuel")]
+    InvalidFuelTank(String),
+    #[error(
+        "The power values of the tile at {0:?} have to be positive and generators need a node"
+    )]
+    InvalidPower((usize, usize)),
+    #[error("There are two pipe nodes named '{0}'")]
+    DuplicateNode(String),
+    #[error("Pipe from '{from}' to '{to}' connects to an unknown node")]
+    UnknownPipeNode { from: String, to: String },
+}
+
+impl AssetLoader for ShipLayoutLoader {
+    type Asset = ShipLayout;
+    type Settings = ();
+    type Error = ShipLayoutError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let layout: ShipLayout = ron::de::from_bytes(&bytes)?;
+        layout.validate()?;
+
+        Ok(layout)
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["ship.ron"]
+    }
+}
+
+impl ShipLayout {
+    fn validate(&self) -> Result<(), ShipLayoutError> {
+        let mut occupied = HashMap::new();
+        for tile in &self.tiles {
+            let scale = tile.scale.unwrap_or(1);
+            for position in tile.positions() {
+                let indices = [
+                    Some(tile.index),
+                    tile.variant.as_ref().map(|variant| variant.index),
+                ];
+                if let Some(index) = indices
+                    .into_iter()
+                    .flatten()
+                    .find(|index| *index >= SHIP_TILE_COUNT)
+                {
+                    return Err(ShipLayoutError::InvalidTileIndex { index, position });
+                }
+                if let Some(variant) = &tile.variant {
+                    if !(0. ..=1.).contains(&variant.chance) {
+                        return Err(ShipLayoutError::InvalidChance {
+                            chance: variant.chance,
+                            position,
+                        });
+                    }
+                }
+                if scale == 0 {
+                    return Err(ShipLayoutError::InvalidScale(position));
+                }
+                if let Some(power) = tile.power {
+                    if !power.is_valid(tile.node.is_some()) {
+                        return Err(ShipLayoutError::InvalidPower(position));
+                    }
+                }
+                if position.1 + 1 < scale {
+                    return Err(ShipLayoutError::OutOfBounds(position));
+                }
+                for cell in tile.cells(position) {
+                    if let Some(first) = occupied.insert((cell, tile.layer), tile.index) {
+                        return Err(ShipLayoutError::Overlap {
+                            cell,
+                            layer: tile.layer,
+                            first,
+                            second: tile.index,
+                        });
+                    }
+                }
+            }
+        }
+
+        let mut nodes = HashSet::new();
+        for tank in &self.fuel_tanks {
+            if !tank.is_valid() {
+                return Err(ShipLayoutError::InvalidFuelTank(tank.name.clone()));
+            }
+            if !nodes.insert(tank.name.as_str()) {
+                return Err(ShipLayoutError::DuplicateNode(tank.name.clone()));
+            }
+            for cell in tank.cells() {
+                if let Some(first) = occupied.insert((cell, 0), tank.fuel_index) {
+                    return Err(ShipLayoutError::Overlap {
+                        cell,
+                        layer: 0,
+                        first,
+                        second: tank.fuel_index,
+                    });
+                }
+            }
+            for index in [tank.fuel_index, tank.surface_index] {
+                if index >= SHIP_TILE_COUNT {
+                    return Err(ShipLayoutError::InvalidTileIndex {
+                        index,
+                        position: tank.position,
+                    });
+                }
+            }
+        }
+
+        // repeated tiles share their node name
+        let tile_nodes: HashSet<&str> = self
+            .tiles
+            .iter()
+            .filter_map(|tile| tile.node.as_deref())
+            .collect();
+        for node in tile_nodes {
+            if !nodes.insert(node) {
+                return Err(ShipLayoutError::DuplicateNode(node.to_owned()));
+            }
+        }
+        for pipe in &self.pipes {
+            if !nodes.contains(pipe.from.as_str()) || !nodes.contains(pipe.to.as_str()) {
+                return Err(ShipLayoutError::UnknownPipeNode {
+                    from: pipe.from.clone(),
+                    to: pipe.to.clone(),
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn validate(layout: &str) -> Result<(), ShipLayoutError> {
+        let layout = format!("#![enable(implicit_some)]\n{layout}");
+        ron::de::from_str::<ShipLayout>(&layout).unwrap().validate()
+    }
+
+    fn tiles(tiles: &str) -> Result<(), ShipLayoutError> {
+        validate(&format!("(tiles: [{tiles}], fuel_tanks: [])"))
+    }
+
+    fn tank(tank: &str) -> String {
+        format!("(name: \"tank\", position: (5, 5), fuel_index: 45, surface_index: 95, {tank})")
+    }
+
+    #[test]
+    fn accepts_the_ship() {
+        let layout = std::fs::read_to_string("assets/ship.ron").unwrap();
+        validate(&layout).unwrap();
+    }
+
+    #[test]
+    fn rejects_tile_indices_outside_of_the_atlas() {
+        assert!(matches!(
+            tiles("(index: 112, position: (1, 2))"),
+            Err(ShipLayoutError::InvalidTileIndex {
+                index: 112,
+                position: (1, 2)
+            })
+        ));
+        assert!(matches!(
+            tiles("(index: 88, position: (1, 2), variant: (index: 200, chance: 0.5))"),
+            Err(ShipLayoutError::InvalidTileIndex { index: 200, .. })
+        ));
+        let layout = "(tiles: [], fuel_tanks: [(name: \"tank\", position: (5, 5), size: (2, 3), fuel_index: 300, surface_index: 95)])";
+        assert!(matches!(
+            validate(layout),
+            Err(ShipLayoutError::InvalidTileIndex { index: 300, .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_chances() {
+        assert!(matches!(
+            tiles("(index: 88, position: (1, 2), variant: (index: 104, chance: 1.5))"),
+            Err(ShipLayoutError::InvalidChance {
+                position: (1, 2),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_scale_zero() {
+        assert!(matches!(
+            tiles("(index: 28, position: (3, 3), scale: 0)"),
+            Err(ShipLayoutError::InvalidScale((3, 3)))
+        ));
+    }
+
+    #[test]
+    fn rejects_scaled_tiles_above_the_top() {
+        assert!(matches!(
+            tiles("(index: 28, position: (3, 0), scale: 2)"),
+            Err(ShipLayoutError::OutOfBounds((3, 0)))
+        ));
+    }
+
+    #[test]
+    fn rejects_overlapping_tiles() {
+        assert!(matches!(
+            tiles("(index: 58, position: (2, 0), repeat: (1, 3)), (index: 28, position: (1, 2), scale: 2)"),
+            Err(ShipLayoutError::Overlap {
+                cell: (2, 1),
+                layer: 0,
+                first: 58,
+                second: 28
+            })
+        ));
+        // tiles on different layers may overlap
+        tiles("(index: 88, position: (2, 2)), (index: 11, position: (2, 2), layer: 1)").unwrap();
+    }
+
+    #[test]
+    fn rejects_invalid_fuel_tanks() {
+        for invalid in [
+            "size: (0, 3)",
+            "size: (2, 1)",
+            "size: (2, 3), capacity: 0.0",
+            "size: (2, 3), capacity: 10.0, fuel: 20.0",
+        ] {
+            let layout = format!("(tiles: [], fuel_tanks: [{}])", tank(invalid));
+            assert!(
+                matches!(validate(&layout), Err(ShipLayoutError::InvalidFuelTank(_))),
+                "{invalid}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_tanks_on_tiles() {
+        let layout = format!(
+            "(tiles: [(index: 88, position: (6, 6))], fuel_tanks: [{}])",
+            tank("size: (2, 3)")
+        );
+        assert!(matches!(
+            validate(&layout),
+            Err(ShipLayoutError::Overlap { cell: (6, 6), .. })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_power() {
+        assert!(matches!(
+            tiles("(index: 41, position: (1, 1), power: SolarPanel(output: 0.0))"),
+            Err(ShipLayoutError::InvalidPower((1, 1)))
+        ));
+        // generators burn fuel from the tanks piped to their node
+        assert!(matches!(
+            tiles("(index: 25, position: (1, 1), power: Generator(output: 10.0, fuel_per_second: 0.2))"),
+            Err(ShipLayoutError::InvalidPower((1, 1)))
+        ));
+    }
+
+    #[test]
+    fn rejects_duplicate_nodes() {
+        let layout = format!(
+            "(tiles: [(index: 83, position: (1, 1), node: \"tank\")], fuel_tanks: [{}])",
+            tank("size: (2, 3)")
+        );
+        assert!(matches!(
+            validate(&layout),
+            Err(ShipLayoutError::DuplicateNode(node)) if node == "tank"
+        ));
+    }
+
+    #[test]
+    fn rejects_pipes_to_unknown_nodes() {
+        let layout = format!(
+            "(tiles: [], fuel_tanks: [{}], pipes: [(from: \"tank\", to: \"engine\")])",
+            tank("size: (2, 3)")
+        );
+        assert!(matches!(
+            validate(&layout),
+            Err(ShipLayoutError::UnknownPipeNode { to, .. }) if to == "engine"
+        ));
+    }
+}
@@ -0,0 +1,143 @@
+use crate::loading::ImageAssets;
+use bevy::prelude::*;
+
+pub struct SkinPlugin;
+
+impl Plugin for SkinPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<ShipSkin>().add_systems(
+            PostUpdate,
+            apply_ship_skin.run_if(resource_exists::<ImageAssets>),
+        );
+    }
+}
+
+/// Look of the ship. Layouts always use indices of `tilemap-ship.png`,
+/// other skins map them to tiles of their own atlas.
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ShipSkin {
+    #[default]
+    Metal,
+    /// `tilemap-ship2.png`
+    Candy,
+}
+
+/// Indices of `tilemap-ship.png` and the `tilemap-ship2.png` tiles that replace them
+const CANDY_INDICES: [(usize, usize); 30] = [
+    // chain with hook
+    (8, 8),
+    (24, 24),
+    (40, 24),
+    // tank input
+    (10, 89),
+    // launch console
+    (26, 90),
+    // ladder
+    (11, 41),
+    (43, 57),
+    // toilet
+    (12, 13),
+    (28, 83),
+    // fuel
+    (45, 27),
+    (95, 11),
+    // walls
+    (58, 57),
+    // pipes and legs
+    (75, 96),
+    (107, 96),
+    // tube exit
+    (79, 73),
+    // platform
+    (103, 5),
+    (88, 6),
+    (104, 22),
+    (106, 7),
+    // farm
+    (17, 17),
+    (18, 18),
+    (19, 19),
+    // airlock and water tap
+    (61, 82),
+    (60, 83),
+    // pump and engine
+    (83, 13),
+    (99, 14),
+    // power
+    (25, 88),
+    (41, 58),
+    (63, 86),
+    (64, 81),
+];
+
+impl ShipSkin {
+    pub const ALL: [ShipSkin; 2] = [ShipSkin::Metal, ShipSkin::Candy];
+
+    pub fn next(self) -> ShipSkin {
+        let current = ShipSkin::ALL.iter().position(|skin| *skin == self).unwrap();
+        ShipSkin::ALL[(current + 1) % ShipSkin::ALL.len()]
+    }
+
+    fn texture(&self, assets: &ImageAssets) -> Handle<Image> {
+        match self {
+            ShipSkin::Metal => assets.tilemap_ship.clone(),
+            ShipSkin::Candy => assets.tilemap_ship2.clone(),
+        }
+    }
+
+    /// Index in this skin's atlas for an index of `tilemap-ship.png`;
+    /// tiles without a replacement keep their index
+    pub fn index(&self, index: usize) -> usize {
+        match self {
+            ShipSkin::Metal => index,
+            ShipSkin::Candy => CANDY_INDICES
+                .iter()
+                .find(|(metal, _)| *metal == index)
+                .map_or(index, |(_, candy)| *candy),
+        }
+    }
+}
+
+/// Tile of `tilemap-ship.png`, drawn with the current `ShipSkin`
+#[derive(Component)]
+pub struct ShipSprite(pub usize);
+
+fn apply_ship_skin(
+    skin: Res<ShipSkin>,
+    assets: Res<ImageAssets>,
+    mut sprites: Query<(Ref<ShipSprite>, &mut Handle<Image>, &mut TextureAtlas)>,
+) {
+    for (sprite, mut texture, mut atlas) in &mut sprites {
+        if !skin.is_changed() && !sprite.is_changed() {
+            continue;
+        }
+        *texture = skin.texture(&assets);
+        atlas.index = skin.index(sprite.0);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ship::ShipLayout;
+
+    #[test]
+    fn candy_skin_replaces_all_ship_tiles() {
+        let layout: ShipLayout =
+            ron::de::from_bytes(&std::fs::read("assets/ship.ron").unwrap()).unwrap();
+        let tiles = layout.tiles.iter().flat_map(|tile| {
+            let variant = tile.variant.as_ref().map(|variant| variant.index);
+            [tile.index].into_iter().chain(variant)
+        });
+        let tanks = layout
+            .fuel_tanks
+            .iter()
+            .flat_map(|tank| [tank.fuel_index, tank.surface_index]);
+        for index in tiles.chain(tanks) {
+            assert!(
+                CANDY_INDICES.iter().any(|(metal, _)| *metal == index),
+                "tile {index} of ship.ron has no candy version"
+            );
+        }
+    }
+}
@@ -0,0 +1,177 @@
+use crate::interaction::InteractionEvent;
+use crate::item::{Inventory, ItemKind};
+use crate::map::{TankInput, Toilet};
+use crate::needs::{Need, Needs};
+use crate::player::Player;
+use crate::power::Consumer;
+use crate::GameState;
+use bevy::prelude::*;
+use std::time::Duration;
+
+pub struct StationPlugin;
+
+impl Plugin for StationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (add_stations, use_stations, process_stations, eat)
+                .chain()
+                .run_if(in_state(GameState::Playing)),
+        );
+    }
+}
+
+/// What a station makes out of what
+#[derive(Clone, Copy, Debug)]
+pub struct Recipe {
+    /// `None` for stations that the player feeds by using them, like the toilet
+    pub input: Option<ItemKind>,
+    pub output: ItemKind,
+    /// Units made out of one unit of input
+    pub amount: u32,
+    pub duration: Duration,
+}
+
+const TOILET: Recipe = Recipe {
+    input: None,
+    output: ItemKind::Waste,
+    amount: 1,
+    duration: Duration::from_secs(2),
+};
+const TANK_INPUT: Recipe = Recipe {
+    input: Some(ItemKind::Crop),
+    output: ItemKind::Fuel,
+    amount: 1,
+    duration: Duration::from_secs(1),
+};
+
+/// Processes one unit of input after another as long as there is room for the output
+#[derive(Component, Debug)]
+pub struct Station {
+    pub recipe: Recipe,
+    /// Units waiting to be processed
+    pub input: u32,
+    /// Processed units waiting to be taken out
+    pub output: u32,
+    /// Maximum number of units in each buffer
+    pub capacity: u32,
+    /// Running while a unit is processed
+    timer: Option<Timer>,
+}
+
+impl Station {
+    pub fn new(recipe: Recipe, capacity: u32) -> Self {
+        Station {
+            recipe,
+            input: 0,
+            output: 0,
+            capacity,
+            timer: None,
+        }
+    }
+
+    pub fn has_room(&self) -> bool {
+        self.input < self.capacity
+    }
+
+    /// Adds a unit to the input buffer; returns false if it is full
+    pub fn insert(&mut self) -> bool {
+        if !self.has_room() {
+            return false;
+        }
+        self.input += 1;
+        true
+    }
+
+    /// Takes a unit out of the output buffer
+    pub fn take(&mut self) -> Option<ItemKind> {
+        if self.output == 0 {
+            return None;
+        }
+        self.output -= 1;
+        Some(self.recipe.output)
+    }
+}
+
+/// Food restored by eating a crop
+const CROP_NUTRITION: f32 = 30.;
+
+fn add_stations(
+    mut commands: Commands,
+    toilets: Query<Entity, Added<Toilet>>,
+    tank_inputs: Query<Entity, Added<TankInput>>,
+) {
+    for entity in &toilets {
+        commands.entity(entity).insert(Station::new(TOILET, 3));
+    }
+    for entity in &tank_inputs {
+        commands.entity(entity).insert(Station::new(TANK_INPUT, 5));
+    }
+}
+
+/// Stations that need power pause while it is off
+fn process_stations(time: Res<Time>, mut stations: Query<(&mut Station, Option<&Consumer>)>) {
+    for (mut station, consumer) in &mut stations {
+        if consumer.is_some_and(|consumer| !consumer.powered) {
+            continue;
+        }
+        let station = &mut *station;
+        if let Some(timer) = &mut station.timer {
+            timer.tick(time.delta());
+            if !timer.finished() {
+                continue;
+            }
+            station.output += station.recipe.amount;
+            station.timer = None;
+        }
+        if station.input > 0 && station.output + station.recipe.amount <= station.capacity {
+            station.input -= 1;
+            station.timer = Some(Timer::new(station.recipe.duration, TimerMode::Once));
+        }
+    }
+}
+
+/// Takes the output of a station, or uses it; tank inputs are fed in [`crate::tank`]
+fn use_stations(
+    mut events: EventReader<InteractionEvent>,
+    mut player: Query<(&mut Inventory, &mut Needs), With<Player>>,
+    mut stations: Query<&mut Station, Without<TankInput>>,
+) {
+    let Ok((mut inventory, mut needs)) = player.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok(mut station) = stations.get_mut(event.entity) else {
+            continue;
+        };
+        if station.output > 0 && inventory.add(station.recipe.output) {
+            station.take();
+            continue;
+        }
+        match station.recipe.input {
+            // the toilet
+            None => {
+                if station.insert() {
+                    needs.restore(Need::Bladder, 100.);
+                }
+            }
+            Some(input) => {
+                if station.has_room() && inventory.remove(input) {
+                    station.insert();
+                }
+            }
+        }
+    }
+}
+
+fn eat(
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut player: Query<(&mut Inventory, &mut Needs), With<Player>>,
+) {
+    let Ok((mut inventory, mut needs)) = player.get_single_mut() else {
+        return;
+    };
+    if keyboard.just_pressed(KeyCode::KeyQ) && inventory.remove(ItemKind::Crop) {
+        needs.restore(Need::Food, CROP_NUTRITION);
+    }
+}
@@ -1,43 +1,517 @@
-use crate::map::TankInput;
+use crate::cooldown::Cooldown;
+use crate::interaction::{Interactable, InteractionEvent};
+use crate::item::{Inventory, ItemKind};
+use crate::loading::TILE_SIZE;
+use crate::map::{FuelTank, TankInput};
+use crate::needs::{Need, Needs};
+use crate::physics::GameLayer;
+use crate::pipes::{PipeNode, PipeNodes, Pipes};
+use crate::player::Player;
+use crate::rng::{GameRng, RngStream};
+use crate::station::Station;
 use crate::GameState;
-use avian2d::collision::CollidingEntities;
+use avian2d::prelude::*;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::utils::HashMap;
+use rand::Rng;
 
 pub struct TankPlugin;
 
 impl Plugin for TankPlugin {
     fn build(&self, app: &mut App) {
-        app.init_resource::<FuelLevel>()
+        app.add_event::<FuelSpill>()
+            .add_event::<TankDamage>()
             .add_systems(OnEnter(GameState::Playing), prep_tank)
-            .add_systems(Update, feed_tank.run_if(in_state(GameState::Playing)));
+            .add_systems(
+                Update,
+                (
+                    feed_tank,
+                    fill_tank,
+                    damage_hull,
+                    add_leaks,
+                    repair_leaks,
+                    leak_fuel,
+                    spill_fuel,
+                    update_puddles,
+                )
+                    .chain()
+                    .run_if(in_state(GameState::Playing)),
+            )
+            .add_systems(OnExit(GameState::Playing), despawn_puddles);
     }
 }
 
-fn prep_tank(mut commands: Commands) {
-    commands.insert_resource(FuelLevel(0.));
-    commands.insert_resource(TankTimer(-1.));
+/// Fuel in a tank; lives on the [`FuelTank`] entity
+#[derive(Component)]
+pub struct FuelLevel {
+    pub amount: f32,
+    pub capacity: f32,
+    /// Fuel lost per second through leaks
+    pub leak_rate: f32,
 }
 
+impl FuelLevel {
+    pub fn new(amount: f32, capacity: f32) -> Self {
+        FuelLevel {
+            amount,
+            capacity,
+            leak_rate: 0.,
+        }
+    }
+
+    /// Adds fuel up to the capacity; returns the fuel that did not fit
+    pub fn fill(&mut self, amount: f32) -> f32 {
+        let added = amount.min(self.capacity - self.amount).max(0.);
+        self.amount += added;
+        amount - added
+    }
+
+    /// How full the tank is between 0 and 1
+    pub fn fraction(&self) -> f32 {
+        if self.capacity <= 0. {
+            return 0.;
+        }
+        (self.amount / self.capacity).clamp(0., 1.)
+    }
+
+    pub fn is_full(&self) -> bool {
+        self.amount >= self.capacity
+    }
+}
+
+/// Fuel that ended up on the floor, e.g. because the tank was already full
+#[derive(Event)]
+pub struct FuelSpill {
+    /// Floor position of the spill
+    pub position: Vec2,
+    pub amount: f32,
+}
+
+/// A tank was hit and starts leaking
+#[derive(Event)]
+pub struct TankDamage {
+    /// Fuel per second lost through the new leak
+    pub leak_rate: f32,
+}
+
+/// Hole in the tank; repaired with a tool
+#[derive(Component)]
+pub struct Leak {
+    pub rate: f32,
+}
+
+/// Fuel and leaks of a tank, kept while the ship is rebuilt after a reload
+pub(crate) struct KeptTank {
+    pub amount: f32,
+    /// Rate and position of each leak
+    leaks: Vec<(f32, Vec3)>,
+}
+
+impl KeptTank {
+    pub(crate) fn spawn_leaks(&self, parent: &mut ChildBuilder) {
+        for &(rate, position) in &self.leaks {
+            spawn_leak(parent, rate, position);
+        }
+    }
+}
+
+/// Kept tanks by name
+pub(crate) type KeptTanks = HashMap<String, KeptTank>;
+
+/// Reads the fuel and leaks of all tanks before they are despawned
+#[derive(SystemParam)]
+pub(crate) struct TankStates<'w, 's> {
+    tanks: Query<
+        'w,
+        's,
+        (
+            &'static PipeNode,
+            &'static FuelLevel,
+            Option<&'static Children>,
+        ),
+    >,
+    leaks: Query<'w, 's, (&'static Leak, &'static Transform)>,
+}
+
+impl TankStates<'_, '_> {
+    pub(crate) fn keep(&self) -> KeptTanks {
+        self.tanks
+            .iter()
+            .map(|(node, fuel_level, children)| {
+                let leaks = self
+                    .leaks
+                    .iter_many(children.into_iter().flatten())
+                    .map(|(leak, transform)| (leak.rate, transform.translation))
+                    .collect();
+                let tank = KeptTank {
+                    amount: fuel_level.amount,
+                    leaks,
+                };
+                (node.0.clone(), tank)
+            })
+            .collect()
+    }
+}
+
+/// Spilled fuel; the fumes take the player's oxygen and it evaporates over time
+#[derive(Component)]
+struct Puddle {
+    amount: f32,
+}
+
+/// Time until the hull is damaged the next time
 #[derive(Resource)]
-struct TankTimer(f64);
+struct HullDamageTimer(Timer);
+
+/// Fuel added to the tank per canister or processed crop
+const FUEL_PER_UNIT: f32 = 10.;
+/// Seconds between two hits on the hull
+const HULL_DAMAGE_INTERVAL: std::ops::Range<f32> = 40.0..90.0;
+/// Fuel per second lost through a new leak
+const LEAK_RATE: std::ops::Range<f32> = 0.5..1.5;
+/// Fuel per second that evaporates from a puddle
+const PUDDLE_EVAPORATION: f32 = 0.5;
+/// Oxygen per second lost while standing in a puddle
+const PUDDLE_FUMES: f32 = 4.;
+const PUDDLE_COLOR: Color = Color::srgb(0.9, 0.5, 0.1);
+
+fn prep_tank(mut commands: Commands, mut rng: ResMut<GameRng>) {
+    commands.insert_resource(HullDamageTimer(next_hull_damage(&mut rng)));
+}
+
+fn next_hull_damage(rng: &mut GameRng) -> Timer {
+    let seconds = rng
+        .stream(RngStream::Hazards)
+        .gen_range(HULL_DAMAGE_INTERVAL);
+    Timer::from_seconds(seconds, TimerMode::Once)
+}
+
+/// Floor below a tank input, where overflowing fuel ends up
+fn floor_below(transform: &GlobalTransform) -> Vec2 {
+    transform.translation().truncate() - Vec2::Y * TILE_SIZE / 2.
+}
+
+/// Pours fuel from a tank input into the tanks it is piped to; what does not fit is spilled
+fn pour(
+    amount: f32,
+    input: Option<&PipeNode>,
+    floor: Vec2,
+    pipes: &Pipes,
+    nodes: &PipeNodes,
+    tanks: &mut Query<&mut FuelLevel>,
+    spills: &mut EventWriter<FuelSpill>,
+) {
+    let mut overflow = amount;
+    let connected = input
+        .into_iter()
+        .flat_map(|input| pipes.downstream(&input.0));
+    for entity in connected.filter_map(|name| nodes.get(name)) {
+        if let Ok(mut tank) = tanks.get_mut(entity) {
+            overflow = tank.fill(overflow);
+        }
+    }
+    if overflow > 0. {
+        spills.send(FuelSpill {
+            position: floor,
+            amount: overflow,
+        });
+    }
+}
 
+/// Pours carried fuel into the tanks, or puts a crop into the tank input to be processed
+#[allow(clippy::type_complexity)]
 fn feed_tank(
-    query: Query<&CollidingEntities, With<TankInput>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut tank: ResMut<FuelLevel>,
-    mut tank_timer: ResMut<TankTimer>,
+    mut events: EventReader<InteractionEvent>,
+    mut query: Query<
+        (
+            &mut Station,
+            Option<&PipeNode>,
+            &GlobalTransform,
+            Option<&mut Cooldown>,
+        ),
+        With<TankInput>,
+    >,
+    mut player: Query<&mut Inventory, With<Player>>,
+    pipes: Res<Pipes>,
+    nodes: PipeNodes,
+    mut tanks: Query<&mut FuelLevel>,
+    mut spills: EventWriter<FuelSpill>,
+) {
+    let Ok(mut inventory) = player.get_single_mut() else {
+        return;
+    };
+    for event in events.read() {
+        let Ok((mut station, node, transform, cooldown)) = query.get_mut(event.entity) else {
+            continue;
+        };
+        let fed = if inventory.remove(ItemKind::Fuel) {
+            let floor = floor_below(transform);
+            pour(
+                FUEL_PER_UNIT,
+                node,
+                floor,
+                &pipes,
+                &nodes,
+                &mut tanks,
+                &mut spills,
+            );
+            true
+        } else if station.has_room() && inventory.remove(ItemKind::Crop) {
+            station.insert();
+            true
+        } else {
+            false
+        };
+        if let Some(mut cooldown) = cooldown.filter(|_| fed) {
+            cooldown.try_use();
+        }
+    }
+}
+
+fn fill_tank(
+    mut query: Query<(&mut Station, Option<&PipeNode>, &GlobalTransform), With<TankInput>>,
+    pipes: Res<Pipes>,
+    nodes: PipeNodes,
+    mut tanks: Query<&mut FuelLevel>,
+    mut spills: EventWriter<FuelSpill>,
+) {
+    for (mut station, node, transform) in &mut query {
+        while station.take().is_some() {
+            let floor = floor_below(transform);
+            pour(
+                FUEL_PER_UNIT,
+                node,
+                floor,
+                &pipes,
+                &nodes,
+                &mut tanks,
+                &mut spills,
+            );
+        }
+    }
+}
+
+fn damage_hull(
     time: Res<Time>,
+    mut timer: ResMut<HullDamageTimer>,
+    mut rng: ResMut<GameRng>,
+    mut damage: EventWriter<TankDamage>,
 ) {
-    for colliding_entities in &query {
-        if !colliding_entities.is_empty()
-            && keyboard.pressed(KeyCode::KeyF)
-            && time.elapsed_seconds_f64() - tank_timer.0 > 1.
+    timer.0.tick(time.delta());
+    if !timer.0.finished() {
+        return;
+    }
+    let leak_rate = rng.stream(RngStream::Hazards).gen_range(LEAK_RATE);
+    damage.send(TankDamage { leak_rate });
+    timer.0 = next_hull_damage(&mut rng);
+}
+
+/// Every hit makes a leak somewhere at the bottom of a random tank
+fn add_leaks(
+    mut commands: Commands,
+    mut damage: EventReader<TankDamage>,
+    mut rng: ResMut<GameRng>,
+    tanks: Query<(Entity, &FuelTank)>,
+) {
+    let tanks: Vec<_> = tanks.iter().collect();
+    if tanks.is_empty() {
+        damage.clear();
+        return;
+    }
+    for damage in damage.read() {
+        let rng = rng.stream(RngStream::Hazards);
+        let (tank_entity, tank) = tanks[rng.gen_range(0..tanks.len())];
+        let interior = tank.interior();
+        let x = rng.gen_range(interior.min.x + 2. ..interior.max.x - 2.);
+        let position = Vec3::new(x, interior.min.y + TILE_SIZE / 2., 1.);
+        info!("The tank is leaking");
+        commands.entity(tank_entity).with_children(|parent| {
+            spawn_leak(parent, damage.leak_rate, position);
+        });
+    }
+}
+
+/// `position` is relative to the tank, which is at the origin
+fn spawn_leak(parent: &mut ChildBuilder, rate: f32, position: Vec3) {
+    parent.spawn((
+        SpriteBundle {
+            sprite: Sprite {
+                color: PUDDLE_COLOR,
+                custom_size: Some(Vec2::splat(3.)),
+                ..default()
+            },
+            transform: Transform::from_translation(position),
+            ..default()
+        },
+        Leak { rate },
+        Interactable::new("repair the leak").with_range(TILE_SIZE * 2.),
+    ));
+}
+
+fn repair_leaks(
+    mut commands: Commands,
+    mut events: EventReader<InteractionEvent>,
+    leaks: Query<(), With<Leak>>,
+    player: Query<&Inventory, With<Player>>,
+) {
+    let Ok(inventory) = player.get_single() else {
+        return;
+    };
+    for event in events.read() {
+        if !leaks.contains(event.entity) {
+            continue;
+        }
+        if inventory.contains(ItemKind::Tool) {
+            commands.entity(event.entity).despawn();
+        } else {
+            info!("A tool is needed to repair the leak");
+        }
+    }
+}
+
+fn leak_fuel(
+    time: Res<Time>,
+    mut leaks: Query<(&Leak, &Parent, &mut Sprite)>,
+    mut tanks: Query<(Entity, &mut FuelLevel)>,
+) {
+    let mut leak_rates = HashMap::new();
+    for (leak, parent, _) in &leaks {
+        *leak_rates.entry(parent.get()).or_insert(0.) += leak.rate;
+    }
+    for (entity, mut tank) in &mut tanks {
+        let leak_rate = leak_rates.get(&entity).copied().unwrap_or(0.);
+        if tank.leak_rate != leak_rate {
+            tank.leak_rate = leak_rate;
+        }
+        if leak_rate > 0. && tank.amount > 0. {
+            tank.amount = (tank.amount - leak_rate * time.delta_seconds()).max(0.);
+        }
+    }
+    // blink to be noticed
+    let alpha = if time.elapsed_seconds().fract() < 0.5 {
+        1.
+    } else {
+        0.3
+    };
+    for (_, _, mut sprite) in &mut leaks {
+        sprite.color.set_alpha(alpha);
+    }
+}
+
+fn spill_fuel(
+    mut commands: Commands,
+    mut spills: EventReader<FuelSpill>,
+    mut puddles: Query<(&mut Puddle, &Transform)>,
+) {
+    for spill in spills.read() {
+        let existing = puddles.iter_mut().find(|(_, transform)| {
+            transform.translation.truncate().distance(spill.position) < TILE_SIZE
+        });
+        if let Some((mut puddle, _)) = existing {
+            puddle.amount += spill.amount;
+            continue;
+        }
+        commands.spawn((
+            SpriteBundle {
+                sprite: Sprite {
+                    color: PUDDLE_COLOR,
+                    ..default()
+                },
+                transform: Transform::from_translation(spill.position.extend(0.5)),
+                ..default()
+            },
+            Puddle {
+                amount: spill.amount,
+            },
+            Sensor,
+            RigidBody::Static,
+            CollisionLayers::new(GameLayer::Ground, GameLayer::Player),
+        ));
+    }
+}
+
+/// Grows and shrinks puddles with their amount of fuel and lets the fumes hurt the player
+fn update_puddles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut puddles: Query<(Entity, &mut Puddle, &mut Sprite, Option<&CollidingEntities>)>,
+    mut player: Query<(Entity, &mut Needs), With<Player>>,
+) {
+    let mut player = player.get_single_mut().ok();
+    for (entity, mut puddle, mut sprite, colliding_entities) in &mut puddles {
+        puddle.amount -= PUDDLE_EVAPORATION * time.delta_seconds();
+        if puddle.amount <= 0. {
+            commands.entity(entity).despawn();
+            continue;
+        }
+        let size = Vec2::new((puddle.amount * 1.5).clamp(6., TILE_SIZE * 3.), 2.);
+        if sprite.custom_size != Some(size) {
+            sprite.custom_size = Some(size);
+            commands
+                .entity(entity)
+                .insert(Collider::rectangle(size.x, size.y));
+        }
+        if let (Some((player, needs)), Some(colliding_entities)) =
+            (player.as_mut(), colliding_entities)
         {
-            tank.0 += 10.;
-            tank_timer.0 = time.elapsed_seconds_f64();
+            if colliding_entities.contains(player) {
+                needs.drain(Need::Oxygen, PUDDLE_FUMES * time.delta_seconds());
+            }
         }
     }
 }
 
-#[derive(Resource, Default)]
-pub struct FuelLevel(pub(crate) f32);
+fn despawn_puddles(mut commands: Commands, puddles: Query<Entity, With<Puddle>>) {
+    for entity in &puddles {
+        commands.entity(entity).despawn();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::time::TimeUpdateStrategy;
+    use std::time::Duration;
+
+    #[test]
+    fn fill_clamps_to_capacity() {
+        let mut tank = FuelLevel::new(90., 100.);
+        assert_eq!(tank.fill(5.), 0.);
+        assert_eq!(tank.amount, 95.);
+        assert_eq!(tank.fill(20.), 15.);
+        assert_eq!(tank.amount, 100.);
+        assert!(tank.is_full());
+        assert_eq!(tank.fill(10.), 10.);
+        assert_eq!(tank.amount, 100.);
+    }
+
+    #[test]
+    fn leaks_drain_fuel() {
+        let mut app = App::new();
+        app.add_plugins(MinimalPlugins)
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+                100,
+            )))
+            .add_systems(Update, leak_fuel);
+        let tank = app
+            .world_mut()
+            .spawn(FuelLevel::new(50., 100.))
+            .with_children(|parent| {
+                parent.spawn((Leak { rate: 2. }, Sprite::default()));
+                parent.spawn((Leak { rate: 3. }, Sprite::default()));
+            })
+            .id();
+
+        for _ in 0..5 {
+            app.update();
+            // both leaks together drain 5 fuel per second
+            let elapsed = app.world().resource::<Time>().elapsed_seconds();
+            let tank = app.world().get::<FuelLevel>(tank).unwrap();
+            assert_eq!(tank.leak_rate, 5.);
+            assert!((tank.amount - (50. - 5. * elapsed)).abs() < 1e-4);
+        }
+        let elapsed = app.world().resource::<Time>().elapsed_seconds();
+        assert!(elapsed > 0.3);
+    }
+}
@@ -0,0 +1,112 @@
+use crate::grid::MapGrid;
+use crate::palette::TileKind;
+use bevy::prelude::*;
+
+/// Size of a generated planet in tiles
+const WIDTH: usize = 64;
+const HEIGHT: usize = 48;
+/// Row of the surface the ship lands on
+const SURFACE: usize = 15;
+/// Columns from the left that stay flat, so the ship has room to land
+const LANDING_PAD: usize = 23;
+/// Columns next to the landing pad in which the terrain rises to its full height
+const SLOPE: f32 = 6.;
+/// Maximum number of rows the surface goes up or down
+const AMPLITUDE: f32 = 6.;
+/// Rows of solid ground below the surface without caves
+const CRUST: usize = 3;
+const CAVE_THRESHOLD: f32 = 0.6;
+const DEPOSIT_CHANCE: f32 = 0.15;
+const DECORATION_CHANCE: f32 = 0.2;
+
+/// Seed of the "Random planet" level; the same seed always generates the same terrain
+#[derive(Resource, Default, Clone, Copy, Debug, PartialEq, Eq)]
+pub struct PlanetSeed(pub u64);
+
+/// Generates a planet with hills on the surface, caves below it and deposits in the caves
+pub fn generate_terrain(seed: PlanetSeed) -> MapGrid {
+    let seed = seed.0;
+    let mut cells = vec![None; WIDTH * HEIGHT];
+    for x in 0..WIDTH {
+        let surface = surface_height(seed, x);
+        for y in surface..HEIGHT {
+            let cave = y >= surface + CRUST
+                && fractal_noise(seed ^ 1, x as f32 / 8., y as f32 / 5.) > CAVE_THRESHOLD;
+            cells[y * WIDTH + x] = Some(if cave {
+                TileKind::Background
+            } else {
+                TileKind::Ground
+            });
+        }
+    }
+
+    for y in 0..HEIGHT {
+        for x in 0..WIDTH {
+            let below = cells.get((y + 1) * WIDTH + x).copied().flatten();
+            let cell = &mut cells[y * WIDTH + x];
+            // deposits lie on the floor of caves
+            if *cell == Some(TileKind::Background)
+                && below == Some(TileKind::Ground)
+                && hash(seed ^ 2, x as i64, y as i64) < DEPOSIT_CHANCE
+            {
+                *cell = Some(TileKind::Deposit);
+            }
+            // plants grow on the surface outside of the landing pad
+            if cell.is_none()
+                && x >= LANDING_PAD
+                && below == Some(TileKind::Ground)
+                && hash(seed ^ 3, x as i64, y as i64) < DECORATION_CHANCE
+            {
+                *cell = Some(TileKind::Decoration);
+            }
+        }
+    }
+
+    MapGrid {
+        width: WIDTH,
+        cells,
+    }
+}
+
+/// Row of the first ground tile in the column
+fn surface_height(seed: u64, x: usize) -> usize {
+    let slope = ((x as f32 - LANDING_PAD as f32) / SLOPE).clamp(0., 1.);
+    let offset = (fractal_noise(seed, x as f32 / 12., 0.) * 2. - 1.) * AMPLITUDE * slope;
+    (SURFACE as f32 + offset).round() as usize
+}
+
+/// Sums a few octaves of value noise; roughly between 0 and 1
+fn fractal_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let mut sum = 0.;
+    let mut amplitude = 0.5;
+    let mut frequency = 1.;
+    for octave in 0..3 {
+        sum += value_noise(seed.wrapping_add(octave), x * frequency, y * frequency) * amplitude;
+        amplitude /= 2.;
+        frequency *= 2.;
+    }
+    sum / 0.875
+}
+
+/// Smoothly interpolated random values on a grid
+fn value_noise(seed: u64, x: f32, y: f32) -> f32 {
+    let (x0, y0) = (x.floor(), y.floor());
+    let smooth = |t: f32| t * t * (3. - 2. * t);
+    let (tx, ty) = (smooth(x - x0), smooth(y - y0));
+    let (x0, y0) = (x0 as i64, y0 as i64);
+
+    let top = hash(seed, x0, y0).lerp(hash(seed, x0 + 1, y0), tx);
+    let bottom = hash(seed, x0, y0 + 1).lerp(hash(seed, x0 + 1, y0 + 1), tx);
+    top.lerp(bottom, ty)
+}
+
+/// Random value between 0 and 1 for a position, based on SplitMix64
+fn hash(seed: u64, x: i64, y: i64) -> f32 {
+    let mut z = seed
+        .wrapping_add((x as u64).wrapping_mul(0x9E37_79B9_7F4A_7C15))
+        .wrapping_add((y as u64).wrapping_mul(0xC2B2_AE3D_27D4_EB4F));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 40) as f32 / (1u64 << 24) as f32
+}
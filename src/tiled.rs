@@ -0,0 +1,902 @@
+use crate::farm::PlotStates;
+use crate::item::{spawn_item, ItemKind};
+use crate::loading::{ImageAssets, LevelAssets, TILE_SIZE};
+use crate::map::{
+    spawn_fuel_tank, Airlock, Console, Engine, Farm, Ladder, Level, MapCommand, MapOrigin, MapTile,
+    ShipTile, SpawnPoint, Spikes, TankInput, Toilet, WaterTap,
+};
+use crate::physics::GameLayer;
+use crate::pipes::{PipeNode, Pipes, Pump};
+use crate::power::insert_power;
+use crate::ship::{
+    FuelTankDefinition, PipeDefinition, PowerDefinition, DEFAULT_TANK_CAPACITY, SHIP_TILE_COUNT,
+};
+use crate::skin::ShipSprite;
+use crate::tank::{KeptTanks, TankStates};
+use crate::GameState;
+use avian2d::prelude::*;
+use bevy::asset::io::Reader;
+use bevy::asset::{AssetLoader, AsyncReadExt, LoadContext};
+use bevy::ecs::system::EntityCommands;
+use bevy::prelude::*;
+use serde::Deserialize;
+use std::path::Path;
+use thiserror::Error;
+
+/// Number of tiles in `tilemap.png` (20 columns, 9 rows)
+pub const TERRAIN_TILE_COUNT: usize = 20 * 9;
+
+const FLIPPED_HORIZONTALLY: u32 = 0x80000000;
+const FLIPPED_VERTICALLY: u32 = 0x40000000;
+const TILE_ID_MASK: u32 = 0x1FFFFFFF;
+
+/// Fuel per second moved by pumps without a `rate` property
+const DEFAULT_PUMP_RATE: f32 = 10.;
+
+pub struct TiledPlugin;
+
+impl Plugin for TiledPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_asset::<TiledMap>()
+            .init_asset_loader::<TiledMapLoader>()
+            .add_systems(
+                Update,
+                reload_tiled_map
+                    .run_if(in_state(GameState::Playing).and_then(resource_equals(Level::Tiled))),
+            );
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn reload_tiled_map(
+    assets: Res<ImageAssets>,
+    level_assets: Res<LevelAssets>,
+    mut tiled_reload: EventReader<AssetEvent<TiledMap>>,
+    mut commands: Commands,
+    tiled_maps: Res<Assets<TiledMap>>,
+    map_tiles: Query<Entity, With<MapTile>>,
+    tanks: TankStates,
+    plots: PlotStates,
+    origin: Res<MapOrigin>,
+) {
+    for event in tiled_reload.read() {
+        if event.is_modified(&level_assets.tiled) {
+            let map = tiled_maps.get(&level_assets.tiled).unwrap();
+            let kept = tanks.keep();
+            commands.insert_resource(plots.keep());
+            for entity in &map_tiles {
+                commands.entity(entity).despawn_recursive();
+            }
+            spawn_tiled_map(map, &mut commands, &assets, &kept, *origin);
+        }
+    }
+}
+
+/// A level made in Tiled and exported as JSON (`.tmj`)
+///
+/// Tile layers with the custom bool property `collider` get static colliders.
+/// Objects are identified by their class: `Ladder`, `Toilet`, `TankInput`, `Console`, `Farm`,
+/// `Airlock`, `WaterTap`, `Item` (with the string property `kind`), `Pump` (with the optional float property
+/// `rate`), `Engine`, `Spikes`, `SpawnPoint` and `FuelTank` (with optional int properties
+/// `fuel_index` and `surface_index` and float properties `capacity` and `fuel`).
+/// Tile objects without a class are drawn as decoration.
+///
+/// `Pipe` objects connect the objects named in their string properties `from` and `to`.
+/// Any object can be part of the power grid with a class property `power` whose type is one
+/// of the [`PowerDefinition`] variants, e.g. `Consumer` with the members `kind`, `demand` and
+/// `priority`. Generators burn fuel from the tanks piped to the object.
+#[derive(Asset, TypePath, Debug)]
+pub struct TiledMap {
+    /// Size in tiles
+    pub width: usize,
+    pub height: usize,
+    pub tile_layers: Vec<TileLayer>,
+    pub objects: Vec<TiledObject>,
+    pub pipes: Vec<PipeDefinition>,
+}
+
+#[derive(Debug)]
+pub struct TileLayer {
+    pub tiles: Vec<TiledTile>,
+    pub collider: bool,
+    pub z: f32,
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TiledTile {
+    pub position: (usize, usize),
+    pub atlas: TiledAtlas,
+    pub index: usize,
+    pub flip_x: bool,
+    pub flip_y: bool,
+}
+
+/// The texture atlases of [`ImageAssets`] that Tiled tilesets can refer to
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TiledAtlas {
+    Terrain,
+    Ship,
+}
+
+#[derive(Debug)]
+pub struct TiledObject {
+    pub name: String,
+    pub kind: ObjectKind,
+    /// Center of the object in pixels, measured from the top left of the map
+    pub center: Vec2,
+    pub size: Vec2,
+    pub tile: Option<TiledTile>,
+    pub fuel_index: usize,
+    pub surface_index: usize,
+    pub capacity: f32,
+    pub fuel: f32,
+    pub power: Option<PowerDefinition>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ObjectKind {
+    Ladder,
+    Toilet,
+    TankInput,
+    Console,
+    Farm,
+    Airlock,
+    WaterTap,
+    /// Item with the string property `kind`
+    Item(ItemKind),
+    /// Pump with the float property `rate`
+    Pump {
+        rate: f32,
+    },
+    Engine,
+    Spikes,
+    SpawnPoint,
+    FuelTank,
+    /// Tile objects without a class are only drawn
+    Decoration,
+}
+
+impl TiledAtlas {
+    fn tile_count(&self) -> usize {
+        match self {
+            TiledAtlas::Terrain => TERRAIN_TILE_COUNT,
+            TiledAtlas::Ship => SHIP_TILE_COUNT,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum TiledMapError {
+    #[error("Could not read Tiled map: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not parse Tiled map: {0}")]
+    Json(#[from] serde_json::Error),
+    #[error("Tiled maps need {TILE_SIZE}x{TILE_SIZE} tiles, but this one uses {0}x{1}")]
+    TileSize(f32, f32),
+    #[error("Layer '{0}' is compressed or encoded; export tile layer data as CSV")]
+    EncodedLayer(String),
+    #[error("Layer '{0}' has no width")]
+    EmptyLayer(String),
+    #[error("Tileset '{0}' is neither 'tilemap' nor 'tilemap-ship'")]
+    UnknownTileset(String),
+    #[error("Tile id {0} does not belong to any tileset")]
+    UnknownTile(u32),
+    #[error("Tile {index} of tileset {atlas:?} is outside of the texture atlas")]
+    InvalidTileIndex { atlas: TiledAtlas, index: usize },
+    #[error("Object '{name}' has the unknown class '{class}'")]
+    UnknownObject { name: String, class: String },
+    #[error("Item '{0}' has no valid 'kind' property")]
+    UnknownItem(String),
+    #[error("Fuel tank '{0}' needs at least one column and two rows and more capacity than fuel")]
+    InvalidFuelTank(String),
+    #[error("Object '{0}' has an invalid 'power' property")]
+    InvalidPower(String),
+    #[error("Pipe '{0}' needs the string properties 'from' and 'to' naming other objects")]
+    InvalidPipe(String),
+}
+
+#[derive(Deserialize)]
+struct MapJson {
+    width: usize,
+    height: usize,
+    tilewidth: f32,
+    tileheight: f32,
+    layers: Vec<LayerJson>,
+    tilesets: Vec<TilesetJson>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type")]
+enum LayerJson {
+    #[serde(rename = "tilelayer")]
+    Tiles {
+        name: String,
+        width: usize,
+        data: serde_json::Value,
+        #[serde(default)]
+        properties: Vec<PropertyJson>,
+    },
+    #[serde(rename = "objectgroup")]
+    Objects { objects: Vec<ObjectJson> },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Deserialize)]
+struct ObjectJson {
+    #[serde(default)]
+    name: String,
+    #[serde(default, rename = "type", alias = "class")]
+    class: String,
+    x: f32,
+    y: f32,
+    #[serde(default)]
+    width: f32,
+    #[serde(default)]
+    height: f32,
+    gid: Option<u32>,
+    #[serde(default)]
+    properties: Vec<PropertyJson>,
+}
+
+#[derive(Deserialize)]
+struct PropertyJson {
+    name: String,
+    /// Name of the custom type of class properties
+    #[serde(default)]
+    propertytype: String,
+    value: serde_json::Value,
+}
+
+#[derive(Deserialize)]
+struct TilesetJson {
+    firstgid: u32,
+    name: Option<String>,
+    source: Option<String>,
+}
+
+fn property<'a>(properties: &'a [PropertyJson], name: &str) -> Option<&'a serde_json::Value> {
+    properties
+        .iter()
+        .find(|property| property.name == name)
+        .map(|property| &property.value)
+}
+
+fn float_property(properties: &[PropertyJson], name: &str) -> Option<f32> {
+    property(properties, name)
+        .and_then(|value| value.as_f64())
+        .map(|value| value as f32)
+}
+
+impl TilesetJson {
+    fn atlas(&self) -> Result<TiledAtlas, TiledMapError> {
+        // external tilesets are referenced by file name, embedded ones by their name
+        let name = self
+            .source
+            .as_deref()
+            .and_then(|source| Path::new(source).file_stem())
+            .and_then(|stem| stem.to_str())
+            .or(self.name.as_deref())
+            .unwrap_or_default();
+        match name {
+            "tilemap" => Ok(TiledAtlas::Terrain),
+            "tilemap-ship" => Ok(TiledAtlas::Ship),
+            _ => Err(TiledMapError::UnknownTileset(name.to_owned())),
+        }
+    }
+}
+
+struct Tilesets(Vec<(u32, TiledAtlas)>);
+
+impl Tilesets {
+    fn tile(&self, gid: u32, position: (usize, usize)) -> Result<Option<TiledTile>, TiledMapError> {
+        let id = gid & TILE_ID_MASK;
+        if id == 0 {
+            return Ok(None);
+        }
+        let (first_gid, atlas) = self
+            .0
+            .iter()
+            .filter(|(first_gid, _)| *first_gid <= id)
+            .max_by_key(|(first_gid, _)| *first_gid)
+            .ok_or(TiledMapError::UnknownTile(id))?;
+        let index = (id - first_gid) as usize;
+        if index >= atlas.tile_count() {
+            return Err(TiledMapError::InvalidTileIndex {
+                atlas: *atlas,
+                index,
+            });
+        }
+
+        Ok(Some(TiledTile {
+            position,
+            atlas: *atlas,
+            index,
+            flip_x: gid & FLIPPED_HORIZONTALLY != 0,
+            flip_y: gid & FLIPPED_VERTICALLY != 0,
+        }))
+    }
+}
+
+#[derive(Default)]
+struct TiledMapLoader;
+
+impl AssetLoader for TiledMapLoader {
+    type Asset = TiledMap;
+    type Settings = ();
+    type Error = TiledMapError;
+
+    async fn load<'a>(
+        &'a self,
+        reader: &'a mut Reader<'_>,
+        _settings: &'a (),
+        _load_context: &'a mut LoadContext<'_>,
+    ) -> Result<Self::Asset, Self::Error> {
+        let mut bytes = Vec::new();
+        reader.read_to_end(&mut bytes).await?;
+        let map: MapJson = serde_json::from_slice(&bytes)?;
+
+        map.try_into()
+    }
+
+    fn extensions(&self) -> &[&str] {
+        &["tmj"]
+    }
+}
+
+impl TryFrom<MapJson> for TiledMap {
+    type Error = TiledMapError;
+
+    fn try_from(map: MapJson) -> Result<Self, Self::Error> {
+        if map.tilewidth != TILE_SIZE || map.tileheight != TILE_SIZE {
+            return Err(TiledMapError::TileSize(map.tilewidth, map.tileheight));
+        }
+        let tilesets = Tilesets(
+            map.tilesets
+                .iter()
+                .map(|tileset| Ok((tileset.firstgid, tileset.atlas()?)))
+                .collect::<Result<_, TiledMapError>>()?,
+        );
+
+        let mut tile_layers = vec![];
+        let mut objects = vec![];
+        let mut pipes = vec![];
+        for (layer_index, layer) in map.layers.into_iter().enumerate() {
+            match layer {
+                LayerJson::Tiles {
+                    name,
+                    width,
+                    data,
+                    properties,
+                } => {
+                    if width == 0 {
+                        return Err(TiledMapError::EmptyLayer(name));
+                    }
+                    let gids: Vec<u32> = serde_json::from_value(data)
+                        .map_err(|_| TiledMapError::EncodedLayer(name))?;
+                    let mut tiles = vec![];
+                    for (cell, gid) in gids.into_iter().enumerate() {
+                        if let Some(tile) = tilesets.tile(gid, (cell % width, cell / width))? {
+                            tiles.push(tile);
+                        }
+                    }
+                    tile_layers.push(TileLayer {
+                        tiles,
+                        collider: property(&properties, "collider")
+                            .and_then(|value| value.as_bool())
+                            .unwrap_or(false),
+                        z: layer_index as f32 * 0.1,
+                    });
+                }
+                LayerJson::Objects {
+                    objects: layer_objects,
+                } => {
+                    for object in layer_objects {
+                        if object.class.is_empty() && object.gid.is_none() {
+                            continue;
+                        }
+                        if object.class == "Pipe" {
+                            pipes.push(object.pipe()?);
+                            continue;
+                        }
+                        objects.push(object.parse(&tilesets)?);
+                    }
+                }
+                LayerJson::Other => (),
+            }
+        }
+
+        for pipe in &pipes {
+            let connects = |name: &str| objects.iter().any(|object| object.name == name);
+            if !connects(&pipe.from) || !connects(&pipe.to) {
+                return Err(TiledMapError::InvalidPipe(format!(
+                    "{} -> {}",
+                    pipe.from, pipe.to
+                )));
+            }
+        }
+
+        Ok(TiledMap {
+            width: map.width,
+            height: map.height,
+            tile_layers,
+            objects,
+            pipes,
+        })
+    }
+}
+
+impl ObjectJson {
+    fn parse(self, tilesets: &Tilesets) -> Result<TiledObject, TiledMapError> {
+        let kind = match self.class.as_str() {
+            "Ladder" => ObjectKind::Ladder,
+            "Toilet" => ObjectKind::Toilet,
+            "TankInput" => ObjectKind::TankInput,
+            "Console" => ObjectKind::Console,
+            "Farm" => ObjectKind::Farm,
+            "Airlock" => ObjectKind::Airlock,
+            "WaterTap" => ObjectKind::WaterTap,
+            "Item" => {
+                let kind = property(&self.properties, "kind")
+                    .and_then(|kind| serde_json::from_value(kind.clone()).ok())
+                    .ok_or_else(|| TiledMapError::UnknownItem(self.name.clone()))?;
+                ObjectKind::Item(kind)
+            }
+            "Pump" => ObjectKind::Pump {
+                rate: float_property(&self.properties, "rate").unwrap_or(DEFAULT_PUMP_RATE),
+            },
+            "Engine" => ObjectKind::Engine,
+            "Spikes" => ObjectKind::Spikes,
+            "SpawnPoint" => ObjectKind::SpawnPoint,
+            "FuelTank" => ObjectKind::FuelTank,
+            "" => ObjectKind::Decoration,
+            _ => {
+                return Err(TiledMapError::UnknownObject {
+                    name: self.name,
+                    class: self.class,
+                })
+            }
+        };
+        let size = Vec2::new(self.width, self.height);
+        let tile = match self.gid {
+            Some(gid) => tilesets.tile(gid, (0, 0))?,
+            None => None,
+        };
+        // tile objects are anchored at their bottom left corner, everything else at the top left
+        let center = if tile.is_some() {
+            Vec2::new(self.x + size.x / 2., self.y - size.y / 2.)
+        } else {
+            Vec2::new(self.x, self.y) + size / 2.
+        };
+        let index_property = |name: &str, default: usize| {
+            property(&self.properties, name)
+                .and_then(|value| value.as_u64())
+                .map_or(default, |value| value as usize)
+        };
+
+        let power = match self
+            .properties
+            .iter()
+            .find(|property| property.name == "power")
+        {
+            Some(property) => {
+                // class properties are tagged like externally tagged enums
+                let tagged = serde_json::json!({ &property.propertytype: property.value });
+                match serde_json::from_value::<PowerDefinition>(tagged) {
+                    Ok(power) if power.is_valid(!self.name.is_empty()) => Some(power),
+                    _ => return Err(TiledMapError::InvalidPower(self.name)),
+                }
+            }
+            None => None,
+        };
+
+        let object = TiledObject {
+            fuel_index: index_property("fuel_index", 45),
+            surface_index: index_property("surface_index", 95),
+            capacity: float_property(&self.properties, "capacity").unwrap_or(DEFAULT_TANK_CAPACITY),
+            fuel: float_property(&self.properties, "fuel").unwrap_or(0.),
+            name: self.name,
+            kind,
+            center,
+            size,
+            tile,
+            power,
+        };
+        if kind == ObjectKind::FuelTank && !object.fuel_tank().is_valid() {
+            return Err(TiledMapError::InvalidFuelTank(object.name));
+        }
+
+        Ok(object)
+    }
+
+    fn pipe(self) -> Result<PipeDefinition, TiledMapError> {
+        let end = |name| {
+            property(&self.properties, name)
+                .and_then(|value| value.as_str())
+                .map(str::to_owned)
+        };
+        match (end("from"), end("to")) {
+            (Some(from), Some(to)) => Ok(PipeDefinition { from, to }),
+            _ => Err(TiledMapError::InvalidPipe(self.name)),
+        }
+    }
+}
+
+impl TiledObject {
+    fn cell(&self, corner: Vec2) -> (usize, usize) {
+        let cell = (corner / TILE_SIZE).round().max(Vec2::ZERO);
+        (cell.x as usize, cell.y as usize)
+    }
+
+    /// The tank covered by a `FuelTank` object, rounded to whole cells
+    fn fuel_tank(&self) -> FuelTankDefinition {
+        let top_left = self.center - self.size / 2.;
+        let position = self.cell(top_left);
+        let bottom_right = self.cell(top_left + self.size);
+        FuelTankDefinition {
+            name: self.name.clone(),
+            position,
+            size: (bottom_right.0 - position.0, bottom_right.1 - position.1),
+            fuel_index: self.fuel_index,
+            surface_index: self.surface_index,
+            capacity: self.capacity,
+            fuel: self.fuel,
+        }
+    }
+}
+
+/// Converts a position in pixels of the Tiled map into world space
+fn world_position(origin: MapOrigin, pixels: Vec2) -> Vec3 {
+    origin.tile_position(0, 0) + Vec3::new(pixels.x - TILE_SIZE / 2., TILE_SIZE / 2. - pixels.y, 0.)
+}
+
+/// Only spawned when the level starts, so reloading the map does not duplicate them
+pub(crate) fn spawn_tiled_items(
+    map: &TiledMap,
+    commands: &mut Commands,
+    assets: &ImageAssets,
+    origin: MapOrigin,
+) {
+    for object in &map.objects {
+        if let ObjectKind::Item(kind) = object.kind {
+            let position = world_position(origin, object.center).truncate();
+            spawn_item(commands, assets, kind, position);
+        }
+    }
+}
+
+pub(crate) fn spawn_tiled_map(
+    map: &TiledMap,
+    commands: &mut Commands,
+    assets: &ImageAssets,
+    kept: &KeptTanks,
+    origin: MapOrigin,
+) {
+    for layer in &map.tile_layers {
+        for tile in &layer.tiles {
+            let mut translation = origin.tile_position(tile.position.0, tile.position.1);
+            translation.z = layer.z;
+            let mut entity = commands.spawn((
+                MapTile,
+                SpatialBundle::from_transform(Transform::from_translation(translation)),
+            ));
+            insert_tile_sprite(&mut entity, tile, assets);
+            if layer.collider {
+                entity.solid_cell(tile.position.0, tile.position.1);
+            }
+        }
+    }
+
+    commands.insert_resource(Pipes(map.pipes.clone()));
+    for object in &map.objects {
+        let mut transform = Transform::from_translation(world_position(origin, object.center));
+        if object.kind == ObjectKind::SpawnPoint {
+            commands.insert_resource(SpawnPoint(transform.translation.truncate()));
+            continue;
+        }
+        if let ObjectKind::Item(_) = object.kind {
+            continue;
+        }
+        if object.kind == ObjectKind::FuelTank {
+            let kept = kept.get(&object.name);
+            spawn_fuel_tank(commands, object.fuel_tank(), assets, kept, origin)
+                .insert((MapTile, ShipTile));
+            continue;
+        }
+
+        let mut collider_size = object.size;
+        if object.tile.is_some() {
+            let scale = object.size.x / TILE_SIZE;
+            transform.scale = Vec3::splat(scale);
+            // colliders are scaled with the sprite
+            collider_size /= scale;
+            transform.translation.z = 0.1;
+        }
+        let mut entity = commands.spawn((MapTile, SpatialBundle::from_transform(transform)));
+        if let Some(tile) = &object.tile {
+            insert_tile_sprite(&mut entity, tile, assets);
+        }
+        if object.kind != ObjectKind::Decoration {
+            entity.insert((
+                Sensor,
+                RigidBody::Static,
+                Collider::rectangle(collider_size.x, collider_size.y),
+                CollisionLayers::new(GameLayer::Ground, GameLayer::Player),
+            ));
+        }
+        if matches!(
+            object.kind,
+            ObjectKind::Ladder
+                | ObjectKind::Toilet
+                | ObjectKind::TankInput
+                | ObjectKind::Console
+                | ObjectKind::Farm
+                | ObjectKind::Airlock
+                | ObjectKind::WaterTap
+                | ObjectKind::Pump { .. }
+                | ObjectKind::Engine
+        ) {
+            entity.insert(ShipTile);
+        }
+        if matches!(
+            object.kind,
+            ObjectKind::TankInput | ObjectKind::Pump { .. } | ObjectKind::Engine
+        ) || matches!(object.power, Some(PowerDefinition::Generator { .. }))
+        {
+            entity.insert(PipeNode(object.name.clone()));
+        }
+        if let Some(power) = object.power {
+            insert_power(&mut entity, power);
+        }
+        match object.kind {
+            ObjectKind::Ladder => {
+                entity.insert(Ladder);
+            }
+            ObjectKind::Toilet => {
+                entity.insert(Toilet);
+            }
+            ObjectKind::TankInput => {
+                entity.insert(TankInput);
+            }
+            ObjectKind::Console => {
+                entity.insert(Console);
+            }
+            ObjectKind::Farm => {
+                entity.insert(Farm);
+            }
+            ObjectKind::Airlock => {
+                entity.insert(Airlock);
+            }
+            ObjectKind::WaterTap => {
+                entity.insert(WaterTap);
+            }
+            ObjectKind::Pump { rate } => {
+                entity.insert(Pump::new(rate));
+            }
+            ObjectKind::Engine => {
+                entity.insert(Engine);
+            }
+            ObjectKind::Spikes => {
+                entity.insert(Spikes);
+            }
+            ObjectKind::Decoration
+            | ObjectKind::SpawnPoint
+            | ObjectKind::FuelTank
+            | ObjectKind::Item(_) => (),
+        }
+    }
+}
+
+fn insert_tile_sprite(entity: &mut EntityCommands, tile: &TiledTile, assets: &ImageAssets) {
+    let (texture, layout) = match tile.atlas {
+        TiledAtlas::Terrain => (&assets.tilemap, &assets.tilemap_layout),
+        TiledAtlas::Ship => {
+            entity.insert((ShipSprite(tile.index), ShipTile));
+            (&assets.tilemap_ship, &assets.tilemap_ship_layout)
+        }
+    };
+    entity.insert((
+        texture.clone(),
+        Sprite {
+            flip_x: tile.flip_x,
+            flip_y: tile.flip_y,
+            ..default()
+        },
+        TextureAtlas {
+            layout: layout.clone(),
+            index: tile.index,
+        },
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a map of 18x18 tiles with the terrain tileset at gid 1 and the ship tileset at gid 181
+    fn parse(layers: &str) -> Result<TiledMap, TiledMapError> {
+        let map = format!(
+            r#"{{
+                "width": 5,
+                "height": 4,
+                "tilewidth": 18,
+                "tileheight": 18,
+                "tilesets": [
+                    {{ "firstgid": 1, "source": "../tilemap.tsx" }},
+                    {{ "firstgid": 181, "name": "tilemap-ship" }}
+                ],
+                "layers": [{layers}]
+            }}"#
+        );
+        serde_json::from_str::<MapJson>(&map)?.try_into()
+    }
+
+    fn objects(objects: &str) -> Result<TiledMap, TiledMapError> {
+        parse(&format!(
+            r#"{{ "type": "objectgroup", "objects": [{objects}] }}"#
+        ))
+    }
+
+    const TANK: &str =
+        r#"{ "name": "tank", "class": "FuelTank", "x": 18, "y": 18, "width": 36, "height": 54 }"#;
+
+    #[test]
+    fn parses_tile_layers() {
+        let flipped = 181 + 28 + FLIPPED_HORIZONTALLY;
+        let map = parse(&format!(
+            r#"{{ "type": "imagelayer" }},
+            {{
+                "type": "tilelayer", "name": "ground", "width": 2, "data": [0, 2, {flipped}, 0],
+                "properties": [{{ "name": "collider", "type": "bool", "value": true }}]
+            }}"#
+        ))
+        .unwrap();
+
+        assert_eq!(map.tile_layers.len(), 1);
+        let layer = &map.tile_layers[0];
+        assert!(layer.collider);
+        assert_eq!(layer.z, 0.1);
+        let tiles: Vec<_> = layer
+            .tiles
+            .iter()
+            .map(|tile| (tile.position, tile.atlas, tile.index, tile.flip_x))
+            .collect();
+        assert_eq!(
+            tiles,
+            [
+                ((1, 0), TiledAtlas::Terrain, 1, false),
+                ((0, 1), TiledAtlas::Ship, 28, true)
+            ]
+        );
+    }
+
+    #[test]
+    fn parses_objects() {
+        let map = objects(&format!(
+            r#"{{ "name": "ladder", "class": "Ladder", "x": 18, "y": 36, "width": 18, "height": 54 }},
+            {{ "gid": 209, "x": 0, "y": 36, "width": 18, "height": 18 }},
+            {{ "name": "canister", "type": "Item", "x": 0, "y": 0,
+                "properties": [{{ "name": "kind", "type": "string", "value": "Fuel" }}] }},
+            {{ "name": "pump", "class": "Pump", "x": 0, "y": 0 }},
+            {{ "name": "pipe", "class": "Pipe", "x": 0, "y": 0, "properties": [
+                {{ "name": "from", "type": "string", "value": "tank" }},
+                {{ "name": "to", "type": "string", "value": "pump" }}
+            ] }},
+            {{ "class": "", "x": 0, "y": 0 }},
+            {TANK}"#
+        ))
+        .unwrap();
+
+        let kinds: Vec<_> = map.objects.iter().map(|object| object.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                ObjectKind::Ladder,
+                ObjectKind::Decoration,
+                ObjectKind::Item(ItemKind::Fuel),
+                ObjectKind::Pump {
+                    rate: DEFAULT_PUMP_RATE
+                },
+                ObjectKind::FuelTank
+            ]
+        );
+        // rectangles are anchored at the top left, tiles at the bottom left
+        assert_eq!(map.objects[0].center, Vec2::new(27., 63.));
+        assert_eq!(map.objects[1].center, Vec2::new(9., 27.));
+        assert_eq!(map.objects[1].tile.unwrap().index, 28);
+        let tank = map.objects[4].fuel_tank();
+        assert_eq!((tank.position, tank.size), ((1, 1), (2, 3)));
+        assert_eq!(tank.capacity, DEFAULT_TANK_CAPACITY);
+        assert_eq!(map.pipes.len(), 1);
+    }
+
+    #[test]
+    fn parses_the_level() {
+        let map = std::fs::read("assets/levels/ship.tmj").unwrap();
+        TiledMap::try_from(serde_json::from_slice::<MapJson>(&map).unwrap()).unwrap();
+    }
+
+    #[test]
+    fn rejects_other_tile_sizes() {
+        let map = r#"{ "width": 5, "height": 4, "tilewidth": 16, "tileheight": 16, "tilesets": [], "layers": [] }"#;
+        let map: MapJson = serde_json::from_str(map).unwrap();
+        assert!(matches!(
+            TiledMap::try_from(map),
+            Err(TiledMapError::TileSize(16., 16.))
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_tile_layers() {
+        assert!(matches!(
+            parse(r#"{ "type": "tilelayer", "name": "empty", "width": 0, "data": [] }"#),
+            Err(TiledMapError::EmptyLayer(name)) if name == "empty"
+        ));
+        assert!(matches!(
+            parse(r#"{ "type": "tilelayer", "name": "base64", "width": 2, "data": "AAAA" }"#),
+            Err(TiledMapError::EncodedLayer(name)) if name == "base64"
+        ));
+        assert!(matches!(
+            parse(r#"{ "type": "tilelayer", "name": "ship", "width": 1, "data": [293] }"#),
+            Err(TiledMapError::InvalidTileIndex {
+                atlas: TiledAtlas::Ship,
+                index: 112
+            })
+        ));
+    }
+
+    #[test]
+    fn rejects_invalid_objects() {
+        assert!(matches!(
+            objects(r#"{ "name": "box", "class": "Box", "x": 0, "y": 0 }"#),
+            Err(TiledMapError::UnknownObject { class, .. }) if class == "Box"
+        ));
+        assert!(matches!(
+            objects(r#"{ "name": "thing", "class": "Item", "x": 0, "y": 0 }"#),
+            Err(TiledMapError::UnknownItem(name)) if name == "thing"
+        ));
+        assert!(matches!(
+            objects(
+                r#"{ "name": "lamp", "class": "Console", "x": 0, "y": 0, "properties": [
+                    { "name": "power", "type": "class", "propertytype": "SolarPanel", "value": { "output": 0 } }
+                ] }"#
+            ),
+            Err(TiledMapError::InvalidPower(name)) if name == "lamp"
+        ));
+        assert!(matches!(
+            objects(&format!(
+                r#"{TANK}, {{ "name": "pipe", "class": "Pipe", "x": 0, "y": 0, "properties": [
+                    {{ "name": "from", "type": "string", "value": "tank" }},
+                    {{ "name": "to", "type": "string", "value": "engine" }}
+                ] }}"#
+            )),
+            Err(TiledMapError::InvalidPipe(pipe)) if pipe == "tank -> engine"
+        ));
+    }
+
+    #[test]
+    fn rejects_too_small_tanks() {
+        let tank = r#"{ "name": "tank", "class": "FuelTank", "x": 18, "y": 18, "width": 36, "height": 18 }"#;
+        assert!(matches!(
+            objects(tank),
+            Err(TiledMapError::InvalidFuelTank(name)) if name == "tank"
+        ));
+    }
+
+    #[test]
+    fn rejects_unknown_tilesets() {
+        let map = r#"{
+            "width": 5,
+            "height": 4,
+            "tilewidth": 18,
+            "tileheight": 18,
+            "tilesets": [{ "firstgid": 1, "name": "tilemap-characters" }],
+            "layers": []
+        }"#;
+        let map: MapJson = serde_json::from_str(map).unwrap();
+        assert!(matches!(
+            TiledMap::try_from(map),
+            Err(TiledMapError::UnknownTileset(name)) if name == "tilemap-characters"
+        ));
+    }
+}
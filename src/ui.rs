@@ -1,3 +1,9 @@
+use crate::cooldown::Cooldown;
+use crate::interaction::{Focus, Interactable};
+use crate::item::{Inventory, ItemKind};
+use crate::needs::{Need, Needs};
+use crate::power::{Consumer, PowerGrid};
+use crate::ship::ConsumerKind;
 use crate::tank::FuelLevel;
 use crate::GameState;
 use bevy::prelude::*;
@@ -7,28 +13,49 @@ pub struct UiPlugin;
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
         app.add_systems(OnEnter(GameState::Playing), setup_ui)
-            .add_systems(Update, update_tank_ui.run_if(in_state(GameState::Playing)));
+            .add_systems(OnExit(GameState::Playing), cleanup_ui)
+            .add_systems(
+                Update,
+                (
+                    update_tank_ui,
+                    update_inventory_ui,
+                    update_needs_ui,
+                    update_power_ui,
+                    update_consumer_ui,
+                    update_prompt_ui,
+                )
+                    .run_if(in_state(GameState::Playing)),
+            );
     }
 }
 
+/// Root of the in-game UI
+#[derive(Component)]
+struct Hud;
+
 fn setup_ui(mut commands: Commands) {
     commands
-        .spawn(NodeBundle {
-            background_color: BackgroundColor(Color::LinearRgba(LinearRgba::new(1., 1., 1., 0.6))),
-            style: Style {
-                width: Val::Px(100.),
-                height: Val::Px(30.),
-                position_type: PositionType::Absolute,
-                top: Val::Px(5.0),
-                right: Val::Px(5.0),
+        .spawn((
+            Hud,
+            NodeBundle {
+                background_color: BackgroundColor(Color::LinearRgba(LinearRgba::new(
+                    1., 1., 1., 0.6,
+                ))),
+                style: Style {
+                    width: Val::Px(220.),
+                    height: Val::Px(155.),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                },
                 ..default()
             },
-            ..default()
-        })
+        ))
         .with_children(|node| {
             node.spawn(
                 TextBundle::from_section(
-                    "Tank:",
+                    "Fuel:",
                     TextStyle {
                         font_size: 20.0,
                         ..default()
@@ -44,7 +71,7 @@ fn setup_ui(mut commands: Commands) {
             );
             node.spawn((
                 TextBundle::from_section(
-                    "0%",
+                    "0/0",
                     TextStyle {
                         font_size: 20.0,
                         ..default()
@@ -60,14 +87,402 @@ fn setup_ui(mut commands: Commands) {
                 }),
                 TankUi,
             ));
+            node.spawn(
+                TextBundle::from_section(
+                    "Leak:",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(30.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                }),
+            );
+            node.spawn((
+                TextBundle::from_section(
+                    "-",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(30.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                }),
+                LeakUi,
+            ));
+            node.spawn(
+                TextBundle::from_section(
+                    "Items:",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(55.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                }),
+            );
+            node.spawn((
+                TextBundle::from_section(
+                    "-",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                )
+                .with_style(Style {
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(55.0),
+                    right: Val::Px(5.0),
+                    ..default()
+                }),
+                InventoryUi,
+            ));
+            for (row, need) in Need::ALL.into_iter().enumerate() {
+                let top = Val::Px(80.0 + 25.0 * row as f32);
+                node.spawn(
+                    TextBundle::from_section(
+                        format!("{need:?}:"),
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top,
+                        left: Val::Px(5.0),
+                        ..default()
+                    }),
+                );
+                node.spawn((
+                    TextBundle::from_section(
+                        "100%",
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top,
+                        right: Val::Px(5.0),
+                        ..default()
+                    }),
+                    NeedUi(need),
+                ));
+            }
+        });
+
+    commands
+        .spawn((
+            Hud,
+            NodeBundle {
+                background_color: BackgroundColor(Color::LinearRgba(LinearRgba::new(
+                    1., 1., 1., 0.6,
+                ))),
+                style: Style {
+                    width: Val::Px(220.),
+                    height: Val::Px(155.),
+                    position_type: PositionType::Absolute,
+                    top: Val::Px(5.0),
+                    left: Val::Px(5.0),
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|node| {
+            let rows = [
+                ("Supply:", PowerUi::Supply),
+                ("Demand:", PowerUi::Demand),
+                ("Battery:", PowerUi::Battery),
+            ];
+            for (row, (label, power_ui)) in rows.into_iter().enumerate() {
+                let top = Val::Px(5.0 + 25.0 * row as f32);
+                node.spawn(
+                    TextBundle::from_section(
+                        label,
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top,
+                        left: Val::Px(5.0),
+                        ..default()
+                    }),
+                );
+                node.spawn((
+                    TextBundle::from_section(
+                        "-",
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top,
+                        right: Val::Px(5.0),
+                        ..default()
+                    }),
+                    power_ui,
+                ));
+            }
+            for (row, kind) in ConsumerKind::ALL.into_iter().enumerate() {
+                let top = Val::Px(80.0 + 25.0 * row as f32);
+                node.spawn(
+                    TextBundle::from_section(
+                        format!("{}:", kind.name()),
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top,
+                        left: Val::Px(5.0),
+                        ..default()
+                    }),
+                );
+                node.spawn((
+                    TextBundle::from_section(
+                        "-",
+                        TextStyle {
+                            font_size: 20.0,
+                            ..default()
+                        },
+                    )
+                    .with_style(Style {
+                        position_type: PositionType::Absolute,
+                        top,
+                        right: Val::Px(5.0),
+                        ..default()
+                    }),
+                    ConsumerUi(kind),
+                ));
+            }
+        });
+
+    commands
+        .spawn((
+            Hud,
+            NodeBundle {
+                style: Style {
+                    width: Val::Percent(100.),
+                    position_type: PositionType::Absolute,
+                    bottom: Val::Px(20.0),
+                    justify_content: JustifyContent::Center,
+                    ..default()
+                },
+                ..default()
+            },
+        ))
+        .with_children(|node| {
+            node.spawn((
+                TextBundle::from_section(
+                    "",
+                    TextStyle {
+                        font_size: 20.0,
+                        ..default()
+                    },
+                ),
+                PromptUi,
+            ));
         });
 }
 
 #[derive(Component)]
 struct TankUi;
 
-fn update_tank_ui(mut tank_ui: Query<&mut Text, With<TankUi>>, fuel_level: Res<FuelLevel>) {
-    if fuel_level.is_changed() {
-        tank_ui.single_mut().sections[0].value = format!("{}%", fuel_level.0.min(100.).round())
+#[derive(Component)]
+struct LeakUi;
+
+#[derive(Component)]
+struct InventoryUi;
+
+#[derive(Component)]
+struct NeedUi(Need);
+
+#[derive(Component, Clone, Copy)]
+enum PowerUi {
+    Supply,
+    Demand,
+    Battery,
+}
+
+/// How many consumers of a kind are powered
+#[derive(Component)]
+struct ConsumerUi(ConsumerKind);
+
+/// Tells the player how to interact with what they are standing at
+#[derive(Component)]
+struct PromptUi;
+
+fn cleanup_ui(mut commands: Commands, hud: Query<Entity, With<Hud>>) {
+    for entity in &hud {
+        commands.entity(entity).despawn_recursive();
+    }
+}
+
+/// Adds up the fuel and leaks of all tanks
+fn update_tank_ui(
+    mut tank_ui: Query<&mut Text, (With<TankUi>, Without<LeakUi>)>,
+    mut leak_ui: Query<&mut Text, (With<LeakUi>, Without<TankUi>)>,
+    tanks: Query<&FuelLevel>,
+    changed: Query<(), Changed<FuelLevel>>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    let (mut amount, mut capacity, mut leak_rate) = (0., 0., 0.);
+    for tank in &tanks {
+        amount += tank.amount;
+        capacity += tank.capacity;
+        leak_rate += tank.leak_rate;
+    }
+    tank_ui.single_mut().sections[0].value = format!("{}/{}", amount.round(), capacity.round());
+    let mut leak = leak_ui.single_mut();
+    if leak_rate > 0. {
+        leak.sections[0].value = format!("{leak_rate:.1}/s");
+        leak.sections[0].style.color = Color::srgb(0.9, 0.1, 0.1);
+    } else {
+        leak.sections[0].value = "-".to_owned();
+        leak.sections[0].style.color = Color::WHITE;
+    }
+}
+
+/// Lists the items in the inventory with the selected slot in brackets
+fn update_inventory_ui(
+    mut inventory_ui: Query<&mut Text, With<InventoryUi>>,
+    inventory: Query<&Inventory, Changed<Inventory>>,
+) {
+    for inventory in &inventory {
+        let slots: Vec<String> = inventory
+            .slots
+            .iter()
+            .enumerate()
+            .map(|(slot, item)| {
+                let name = item.map_or("-", ItemKind::name);
+                if slot == inventory.selected {
+                    format!("[{name}]")
+                } else {
+                    name.to_owned()
+                }
+            })
+            .collect();
+        inventory_ui.single_mut().sections[0].value = slots.join(" ");
+    }
+}
+
+fn update_needs_ui(
+    mut needs_ui: Query<(&mut Text, &NeedUi)>,
+    needs: Query<&Needs, Changed<Needs>>,
+) {
+    let Ok(needs) = needs.get_single() else {
+        return;
+    };
+    for (mut text, need_ui) in &mut needs_ui {
+        let value = needs.get(need_ui.0);
+        text.sections[0].value = format!("{}%", value.ceil());
+        text.sections[0].style.color = if value < 25. {
+            Color::srgb(0.9, 0.1, 0.1)
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn update_power_ui(mut power_ui: Query<(&mut Text, &PowerUi)>, grid: Res<PowerGrid>) {
+    if !grid.is_changed() {
+        return;
+    }
+    for (mut text, power_ui) in &mut power_ui {
+        let (value, warning) = match power_ui {
+            PowerUi::Supply => (format!("{:.0}", grid.supply), false),
+            PowerUi::Demand => (format!("{:.0}", grid.demand), grid.demand > grid.supply),
+            PowerUi::Battery if grid.capacity > 0. => {
+                let percent = grid.stored / grid.capacity * 100.;
+                (format!("{}%", percent.ceil()), percent < 25.)
+            }
+            PowerUi::Battery => ("-".to_owned(), false),
+        };
+        text.sections[0].value = value;
+        text.sections[0].style.color = if warning {
+            Color::srgb(0.9, 0.1, 0.1)
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn update_consumer_ui(
+    mut consumer_ui: Query<(&mut Text, &ConsumerUi)>,
+    consumers: Query<&Consumer>,
+    changed: Query<(), Changed<Consumer>>,
+) {
+    if changed.is_empty() {
+        return;
+    }
+    for (mut text, consumer_ui) in &mut consumer_ui {
+        let (mut powered, mut total) = (0, 0);
+        for consumer in consumers
+            .iter()
+            .filter(|consumer| consumer.kind == consumer_ui.0)
+        {
+            total += 1;
+            if consumer.powered {
+                powered += 1;
+            }
+        }
+        text.sections[0].value = if total == 0 {
+            "-".to_owned()
+        } else {
+            format!("{powered}/{total}")
+        };
+        text.sections[0].style.color = if powered < total {
+            Color::srgb(0.9, 0.1, 0.1)
+        } else {
+            Color::WHITE
+        };
+    }
+}
+
+fn update_prompt_ui(
+    mut prompt_ui: Query<&mut Text, With<PromptUi>>,
+    focus: Res<Focus>,
+    interactables: Query<(&Interactable, Option<&Cooldown>)>,
+) {
+    let Ok(mut text) = prompt_ui.get_single_mut() else {
+        return;
+    };
+    // compared every frame as the prompt and charges of the focused entity can change too
+    let prompt = match focus.0.and_then(|entity| interactables.get(entity).ok()) {
+        Some((interactable, Some(cooldown))) => format!(
+            "{} ({}/{})",
+            interactable.prompt_text(),
+            cooldown.charges,
+            cooldown.max_charges
+        ),
+        Some((interactable, None)) => interactable.prompt_text(),
+        None => String::new(),
+    };
+    if text.sections[0].value != prompt {
+        text.sections[0].value = prompt;
     }
 }